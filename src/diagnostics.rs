@@ -0,0 +1,294 @@
+use super::lexer::*;
+use super::parser::*;
+use super::sema::*;
+use super::types::MAX_SIZE;
+use std::fmt::Write;
+
+// An error about a span of the source, with notes that explain it further.
+#[derive(Debug, PartialEq)]
+pub struct Diagnostic {
+    pub message: String,
+    // None for an error about the whole program.
+    pub loc: Option<Loc>,
+    pub notes: Vec<String>,
+}
+
+impl Diagnostic {
+    pub fn new(message: String, loc: Option<Loc>) -> Self {
+        Diagnostic {
+            message,
+            loc,
+            notes: Vec::new(),
+        }
+    }
+
+    pub fn with_note(mut self, note: String) -> Self {
+        self.notes.push(note);
+        self
+    }
+}
+
+// The text of a program, under the name that diagnostics refer to it by.
+// A `Loc` counts characters from the start of the text.
+pub struct Source<'a> {
+    name: &'a str,
+    chars: Vec<char>,
+}
+
+impl<'a> Source<'a> {
+    pub fn new(name: &'a str, text: &str) -> Self {
+        Source {
+            name,
+            chars: text.chars().collect(),
+        }
+    }
+
+    // Converts a position into a line and a column, both counted from 1.
+    pub fn line_col(&self, pos: usize) -> (usize, usize) {
+        let before = &self.chars[..pos.min(self.chars.len())];
+        let line = before.iter().filter(|&&c| c == '\n').count() + 1;
+        let start = before.iter().rposition(|&c| c == '\n').map_or(0, |i| i + 1);
+        (line, before.len() - start + 1)
+    }
+
+    // The text that `loc` spans.
+    fn spelling(&self, loc: Loc) -> String {
+        let end = loc.1.min(self.chars.len());
+        self.chars[loc.0.min(end)..end].iter().collect()
+    }
+
+    // Formats the diagnostic in the style of rustc: the message, the
+    // position, and the line of the source with the span underlined.
+    //
+    //     error: use of undeclared identifier `x`
+    //      --> <input>:1:21
+    //       |
+    //     1 | int main() { return x; }
+    //       |                     ^
+    pub fn render(&self, diag: &Diagnostic) -> String {
+        let mut out = String::new();
+        writeln!(out, "error: {}", diag.message).unwrap();
+        let loc = match diag.loc {
+            Some(loc) => loc,
+            None => {
+                for note in diag.notes.iter() {
+                    writeln!(out, "  = note: {}", note).unwrap();
+                }
+                return out;
+            }
+        };
+        let len = self.chars.len();
+        let (start, end) = (loc.0.min(len), loc.1.min(len));
+        let (line, col) = self.line_col(start);
+        let line_start = start + 1 - col;
+        let line_end = self.chars[start..]
+            .iter()
+            .position(|&c| c == '\n')
+            .map_or(len, |i| start + i);
+        let text: String = self.chars[line_start..line_end].iter().collect();
+        // A tab in the source stays a tab under it, so that the caret lines up.
+        let indent: String = self.chars[line_start..start]
+            .iter()
+            .map(|&c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+        // A span that runs onto later lines is underlined to the end of its first.
+        let width = end.min(line_end).saturating_sub(start).max(1);
+        let gutter = " ".repeat(line.to_string().len());
+
+        writeln!(out, "{}--> {}:{}:{}", gutter, self.name, line, col).unwrap();
+        writeln!(out, "{} |", gutter).unwrap();
+        writeln!(out, "{} | {}", line, text).unwrap();
+        writeln!(out, "{} | {}{}", gutter, indent, "^".repeat(width)).unwrap();
+        for note in diag.notes.iter() {
+            writeln!(out, "{} = note: {}", gutter, note).unwrap();
+        }
+        out
+    }
+
+    pub fn lex_error(&self, err: &LexError) -> Diagnostic {
+        let message = match err.value {
+            // Control characters would not show up in the message.
+            LexErrorKind::InvalidChar(c) if c.is_control() => {
+                format!("invalid character `{}`", c.escape_default())
+            }
+            LexErrorKind::InvalidChar(c) => format!("invalid character `{}`", c),
+            LexErrorKind::InvalidNumber => "invalid number literal".to_string(),
+            LexErrorKind::UnclosedStr => "unterminated string literal".to_string(),
+            LexErrorKind::UnclosedComment => "unterminated block comment".to_string(),
+            LexErrorKind::Eof => "unexpected end of input".to_string(),
+        };
+        Diagnostic::new(message, Some(err.loc))
+    }
+
+    // Describes a parse error, quoting the offending token from the source.
+    pub fn parse_error(&self, err: &ParseError) -> Diagnostic {
+        let (message, tok) = match err {
+            ParseError::Unexpected(tok) => {
+                (format!("unexpected `{}`", self.spelling(tok.loc)), tok)
+            }
+            ParseError::NotClosingParen(tok) => (
+                format!("expected `)`, found `{}`", self.spelling(tok.loc)),
+                tok,
+            ),
+            ParseError::NotInLoop(tok) if tok.value == TokenKind::Break => {
+                ("`break` outside of a loop or switch".to_string(), tok)
+            }
+            ParseError::NotInLoop(tok) => ("`continue` outside of a loop".to_string(), tok),
+            ParseError::NotInSwitch(tok) => (
+                format!("`{}` label outside of a switch", self.spelling(tok.loc)),
+                tok,
+            ),
+            ParseError::DuplicateDefault(tok) => {
+                ("multiple `default` labels in one switch".to_string(), tok)
+            }
+            ParseError::UndefinedLabel(tok) => (
+                format!("use of undeclared label `{}`", self.spelling(tok.loc)),
+                tok,
+            ),
+            ParseError::NotConstant(tok) => {
+                let message = format!(
+                    "value of enumerator `{}` is not an integer constant",
+                    self.spelling(tok.loc)
+                );
+                let note = "an enumerator must have a value that fits in `int`";
+                return Diagnostic::new(message, Some(tok.loc)).with_note(note.to_string());
+            }
+            ParseError::TooManyParams(tok) | ParseError::TooManyArgs(tok) => {
+                let message = match err {
+                    ParseError::TooManyParams(_) => "too many parameters",
+                    _ => "too many arguments",
+                };
+                let note = format!("at most {} are supported", MAX_ARGS);
+                return Diagnostic::new(message.to_string(), Some(tok.loc)).with_note(note);
+            }
+            ParseError::UndefinedVariable(tok) => (
+                format!("use of undeclared identifier `{}`", self.spelling(tok.loc)),
+                tok,
+            ),
+            ParseError::Redefinition(tok) => {
+                (format!("redefinition of `{}`", self.spelling(tok.loc)), tok)
+            }
+            ParseError::IncompleteType(tok) => (
+                format!("`{}` has an incomplete type", self.spelling(tok.loc)),
+                tok,
+            ),
+            ParseError::TooLarge(tok) => {
+                let message = match tok.value {
+                    TokenKind::Int(..) => "array is too large".to_string(),
+                    TokenKind::Ident(_) => format!(
+                        "`{}` does not fit in the size limit",
+                        self.spelling(tok.loc)
+                    ),
+                    // An operator that needs a temporary in the stack frame.
+                    _ => format!(
+                        "no room in the stack frame for the result of `{}`",
+                        self.spelling(tok.loc)
+                    ),
+                };
+                let note = format!("objects and stack frames can be at most {} bytes", MAX_SIZE);
+                return Diagnostic::new(message, Some(tok.loc)).with_note(note);
+            }
+            ParseError::Eof => {
+                let end = self.chars.len();
+                let message = "unexpected end of input".to_string();
+                return Diagnostic::new(message, Some(Loc(end, end)));
+            }
+            ParseError::TooManyErrors => {
+                return Diagnostic::new("too many errors, stopping now".to_string(), None);
+            }
+        };
+        Diagnostic::new(message, Some(tok.loc))
+    }
+
+    pub fn sema_error(&self, err: &SemaError) -> Diagnostic {
+        let message = match &err.value {
+            SemaErrorKind::NotLvalue => "expression is not an lvalue".to_string(),
+            SemaErrorKind::NotPointer(ty) => {
+                format!("cannot dereference a value of type `{}`", ty)
+            }
+            SemaErrorKind::InvalidOperands(lhs, rhs) => {
+                format!("invalid operands of types `{}` and `{}`", lhs, rhs)
+            }
+            SemaErrorKind::InvalidOperand(ty) => format!("invalid operand of type `{}`", ty),
+            SemaErrorKind::NotStruct(ty) => {
+                format!(
+                    "member access into `{}`, which is not a struct or union",
+                    ty
+                )
+            }
+            SemaErrorKind::NoSuchMember(name) => format!("no member named `{}`", name),
+            SemaErrorKind::NotConstant => "expression is not a compile-time constant".to_string(),
+            SemaErrorKind::DuplicateCase(value) => format!("duplicate case value `{}`", value),
+            SemaErrorKind::InvalidInitializer(ty) => {
+                format!("invalid initializer for type `{}`", ty)
+            }
+            SemaErrorKind::ReadOnly => "cannot assign to a const-qualified object".to_string(),
+            SemaErrorKind::StructByValue(ty) => {
+                let message = format!("`{}` cannot be passed to or returned from a function", ty);
+                let note = "pass a pointer to it instead";
+                return Diagnostic::new(message, Some(err.loc)).with_note(note.to_string());
+            }
+        };
+        Diagnostic::new(message, Some(err.loc))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_line_col() {
+        let source = Source::new("a.c", "ab\ncd\n\ne");
+        assert_eq!(source.line_col(0), (1, 1));
+        assert_eq!(source.line_col(2), (1, 3));
+        assert_eq!(source.line_col(3), (2, 1));
+        assert_eq!(source.line_col(7), (4, 1));
+        assert_eq!(source.line_col(100), (4, 2));
+    }
+
+    #[test]
+    fn test_render() {
+        let source = Source::new("<input>", "int main() {\n\treturn x;\n}");
+        let diag = Diagnostic::new(
+            "use of undeclared identifier `x`".to_string(),
+            Some(Loc(21, 22)),
+        )
+        .with_note("declare it first".to_string());
+        assert_eq!(
+            source.render(&diag),
+            "error: use of undeclared identifier `x`\n \
+             --> <input>:2:9\n  \
+             |\n\
+             2 | \treturn x;\n  \
+             | \t       ^\n  \
+             = note: declare it first\n"
+        );
+        // The end of the input is just past the last character.
+        let diag = source.parse_error(&ParseError::Eof);
+        assert!(source.render(&diag).contains("--> <input>:3:2\n"));
+        let diag = source.parse_error(&ParseError::TooManyErrors);
+        assert_eq!(
+            source.render(&diag),
+            "error: too many errors, stopping now\n"
+        );
+    }
+
+    #[test]
+    fn test_messages() {
+        let input = "int main() { int a; int a; return b; }";
+        let source = Source::new("<input>", input);
+        let errors = parse(lex(input).unwrap()).unwrap_err();
+        let diag = source.parse_error(&errors[0]);
+        assert_eq!(diag.message, "redefinition of `a`");
+        assert_eq!(diag.loc, Some(Loc(24, 25)));
+        let input = "int main() { int *p; return p * 2; }";
+        let source = Source::new("<input>", input);
+        let mut program = parse(lex(input).unwrap()).unwrap();
+        let err = analyze(&mut program).unwrap_err();
+        assert_eq!(
+            source.sema_error(&err).message,
+            "invalid operands of types `int *` and `int`"
+        );
+    }
+}
@@ -1,96 +1,855 @@
 use super::parser::*;
 
-pub fn code_gen(code: Vec<Node>) {
-    print!(".intel_syntax noprefix\n");
-    print!(".global main\n");
-    print!("main:\n");
+use super::types::*;
 
-    print!("  push rbp\n");
-    print!("  mov rbp, rsp\n");
-    print!("  sub rsp, 208\n");
+const ARG_REGS: [&str; MAX_ARGS] = ["rdi", "rsi", "rdx", "rcx", "r8", "r9"];
+const ARG_REGS32: [&str; MAX_ARGS] = ["edi", "esi", "edx", "ecx", "r8d", "r9d"];
+const ARG_REGS16: [&str; MAX_ARGS] = ["di", "si", "dx", "cx", "r8w", "r9w"];
+const ARG_REGS8: [&str; MAX_ARGS] = ["dil", "sil", "dl", "cl", "r8b", "r9b"];
+const ARG_XMMS: [&str; MAX_ARGS] = ["xmm0", "xmm1", "xmm2", "xmm3", "xmm4", "xmm5"];
 
-    for node in code {
-        gen(node);
-        print!("  pop rax\n");
+#[derive(Debug, Default)]
+struct Context {
+    label_seq: usize,
+    // Label numbers of the loops and switches that `break` may leave,
+    // innermost last.
+    breaks: Vec<usize>,
+    // Label numbers of the enclosing loops, innermost last.
+    continues: Vec<usize>,
+    // Label numbers of the enclosing switches, innermost last.
+    switches: Vec<usize>,
+    // Name of the function being generated.
+    func_name: String,
+    // Contents of the string literals, labelled by their index.
+    strings: Vec<Vec<u8>>,
+}
+
+impl Context {
+    fn new_label(&mut self) -> usize {
+        self.label_seq += 1;
+        self.label_seq
+    }
+
+    fn add_string(&mut self, bytes: Vec<u8>) -> usize {
+        self.strings.push(bytes);
+        self.strings.len() - 1
+    }
+}
+
+pub fn code_gen(program: Program) {
+    let mut ctx = Context::default();
+
+    println!(".intel_syntax noprefix");
+    println!(".text");
+    for func in program.functions {
+        gen_function(func, &mut ctx);
+    }
+
+    for global in program.globals {
+        gen_global(global, &mut ctx);
     }
 
-    print!("  mov rsp, rbp\n");
-    print!("  pop rbp\n");
-    print!("  ret\n");
+    println!(".data");
+    for (i, bytes) in ctx.strings.iter().enumerate() {
+        println!(".LC{}:", i);
+        for b in bytes {
+            println!("  .byte {}", b);
+        }
+        println!("  .byte 0");
+    }
 }
 
-fn gen(node: Node) {
+fn gen_function(func: Function, ctx: &mut Context) {
+    ctx.func_name = func.name;
+
+    if !func.is_static {
+        println!(".global {}", ctx.func_name);
+    }
+    println!("{}:", ctx.func_name);
+
+    println!("  push rbp");
+    println!("  mov rbp, rsp");
+    println!("  sub rsp, {}", func.locals.stack_size());
+
+    // Integer and floating parameters are numbered separately.
+    let (mut gp, mut fp) = (0, 0);
+    for param in func.params.iter() {
+        let ty = param.ty.unqualified();
+        if ty.is_flonum() {
+            println!("  mov{} [rbp-{}], {}", sse(ty), param.offset, ARG_XMMS[fp]);
+            fp += 1;
+            continue;
+        }
+        let reg = match ty.size() {
+            1 => ARG_REGS8[gp],
+            2 => ARG_REGS16[gp],
+            4 => ARG_REGS32[gp],
+            _ => ARG_REGS[gp],
+        };
+        println!("  mov [rbp-{}], {}", param.offset, reg);
+        gp += 1;
+    }
+
+    gen_stmt(func.body, ctx);
+
+    println!(".Lreturn.{}:", ctx.func_name);
+    println!("  mov rsp, rbp");
+    println!("  pop rbp");
+    println!("  ret");
+}
+
+fn gen_global(global: Global, ctx: &mut Context) {
+    let var = global.var;
+    match global.init {
+        Some(_) => println!(".data"),
+        None => println!(".bss"),
+    }
+    if !global.is_static {
+        println!(".global {}", var.name);
+    }
+    println!(".align {}", var.ty.align());
+    println!("{}:", var.name);
+    let data = match global.init {
+        Some(Initializer::Data(data)) => data,
+        Some(_) => unreachable!(),
+        None => {
+            println!("  .zero {}", var.ty.size());
+            return;
+        }
+    };
+    let mut relocs = data.relocs.into_iter().peekable();
+    let mut pos = 0;
+    while pos < data.bytes.len() {
+        match relocs.next_if(|r| r.offset == pos) {
+            Some(reloc) => {
+                let label = match reloc.target {
+                    RelocTarget::Global(name) => name,
+                    RelocTarget::Str(bytes) => format!(".LC{}", ctx.add_string(bytes)),
+                };
+                println!("  .quad {}{:+}", label, reloc.addend);
+                pos += 8;
+            }
+            None => {
+                println!("  .byte {}", data.bytes[pos]);
+                pos += 1;
+            }
+        }
+    }
+}
+
+fn gen_stmt(node: Node, ctx: &mut Context) {
     match node.value {
         NodeKind::Return(expr) => {
-            gen(*expr);
-            print!("  pop rax\n");
-            print!("  mov rsp, rbp\n");
-            print!("  pop rbp\n");
-            print!("  ret\n");
+            let ty = expr.ty.clone().unwrap();
+            gen(*expr, ctx);
+            println!("  pop rax");
+            if ty.is_flonum() {
+                println!("  movq xmm0, rax");
+            }
+            println!("  jmp .Lreturn.{}", ctx.func_name);
+        }
+        NodeKind::If { cond, then, els } => {
+            let label = ctx.new_label();
+            gen_cond(*cond, ctx);
+            println!("  je .Lelse{}", label);
+            gen_stmt(*then, ctx);
+            println!("  jmp .Lend{}", label);
+            println!(".Lelse{}:", label);
+            if let Some(els) = els {
+                gen_stmt(*els, ctx);
+            }
+            println!(".Lend{}:", label);
         }
+        NodeKind::Block(stmts) => {
+            for stmt in stmts {
+                gen_stmt(stmt, ctx);
+            }
+        }
+        NodeKind::While { cond, body } => {
+            let label = ctx.new_label();
+            println!(".Lcontinue{}:", label);
+            gen_cond(*cond, ctx);
+            println!("  je .Lbreak{}", label);
+            gen_loop_body(*body, label, ctx);
+            println!("  jmp .Lcontinue{}", label);
+            println!(".Lbreak{}:", label);
+        }
+        NodeKind::DoWhile { body, cond } => {
+            let label = ctx.new_label();
+            println!(".Lbegin{}:", label);
+            gen_loop_body(*body, label, ctx);
+            println!(".Lcontinue{}:", label);
+            gen_cond(*cond, ctx);
+            println!("  jne .Lbegin{}", label);
+            println!(".Lbreak{}:", label);
+        }
+        NodeKind::For {
+            init,
+            cond,
+            step,
+            body,
+        } => {
+            let label = ctx.new_label();
+            if let Some(init) = init {
+                gen_stmt(*init, ctx);
+            }
+            println!(".Lbegin{}:", label);
+            if let Some(cond) = cond {
+                gen_cond(*cond, ctx);
+                println!("  je .Lbreak{}", label);
+            }
+            gen_loop_body(*body, label, ctx);
+            println!(".Lcontinue{}:", label);
+            if let Some(step) = step {
+                gen_stmt(*step, ctx);
+            }
+            println!("  jmp .Lbegin{}", label);
+            println!(".Lbreak{}:", label);
+        }
+        NodeKind::Switch {
+            cond,
+            body,
+            cases,
+            has_default,
+        } => {
+            let label = ctx.new_label();
+            let ty = cond.ty.clone().unwrap();
+            gen(*cond, ctx);
+            println!("  pop rax");
+            let otherwise = if has_default {
+                format!(".Ldefault{}", label)
+            } else {
+                format!(".Lbreak{}", label)
+            };
+            match jump_table_range(&cases, &ty) {
+                Some((min, len)) => gen_jump_table(&cases, &ty, min, len, label, &otherwise),
+                None => {
+                    for (i, case) in cases.iter().enumerate() {
+                        println!("  mov rdi, {}", case.value);
+                        println!("  cmp rax, rdi");
+                        println!("  je .Lcase{}.{}", label, i);
+                    }
+                    println!("  jmp {}", otherwise);
+                }
+            }
+            ctx.breaks.push(label);
+            ctx.switches.push(label);
+            gen_stmt(*body, ctx);
+            ctx.switches.pop();
+            ctx.breaks.pop();
+            println!(".Lbreak{}:", label);
+        }
+        NodeKind::Case { index, stmt } => {
+            println!(".Lcase{}.{}:", ctx.switches.last().unwrap(), index);
+            gen_stmt(*stmt, ctx);
+        }
+        NodeKind::Default(stmt) => {
+            println!(".Ldefault{}:", ctx.switches.last().unwrap());
+            gen_stmt(*stmt, ctx);
+        }
+        NodeKind::Label { name, stmt } => {
+            println!(".Llabel.{}.{}:", ctx.func_name, name);
+            gen_stmt(*stmt, ctx);
+        }
+        NodeKind::Goto(name) => {
+            println!("  jmp .Llabel.{}.{}", ctx.func_name, name);
+        }
+        NodeKind::Break => {
+            println!("  jmp .Lbreak{}", ctx.breaks.last().unwrap());
+        }
+        NodeKind::Continue => {
+            println!("  jmp .Lcontinue{}", ctx.continues.last().unwrap());
+        }
+        _ => {
+            gen(node, ctx);
+            println!("  pop rax");
+        }
+    }
+}
+
+// Evaluates a condition and compares it with zero, so that `je` jumps if it
+// is false. A floating NaN is true, as it is unequal to zero.
+fn gen_cond(node: Node, ctx: &mut Context) {
+    let ty = node.ty.clone().unwrap();
+    gen(node, ctx);
+    println!("  pop rax");
+    if ty.is_flonum() {
+        println!("  movq xmm0, rax");
+        println!("  xorps xmm1, xmm1");
+        println!("  ucomi{} xmm0, xmm1", sse(&ty));
+        println!("  setne al");
+        println!("  setp dl");
+        println!("  or al, dl");
+        println!("  movzb rax, al");
+    }
+    println!("  cmp rax, 0");
+}
+
+fn gen_loop_body(body: Node, label: usize, ctx: &mut Context) {
+    ctx.breaks.push(label);
+    ctx.continues.push(label);
+    gen_stmt(body, ctx);
+    ctx.continues.pop();
+    ctx.breaks.pop();
+}
+
+// Returns the smallest case value and the number of entries of a jump table
+// indexed by the condition of a switch, if the cases are many and dense
+// enough to fill at least half of the table. Values compare as integers of
+// the condition type `ty`.
+fn jump_table_range(cases: &[SwitchCase], ty: &Type) -> Option<(i128, i128)> {
+    if cases.len() < 4 {
+        return None;
+    }
+    let keys = cases.iter().map(|case| case_key(case.value, ty));
+    let min = keys.clone().min().unwrap();
+    let len = keys.max().unwrap() - min + 1;
+    if len <= 2 * cases.len() as i128 {
+        Some((min, len))
+    } else {
+        None
+    }
+}
+
+fn case_key(value: i64, ty: &Type) -> i128 {
+    if ty.is_unsigned() {
+        value as u64 as i128
+    } else {
+        value as i128
+    }
+}
+
+// Jumps to the case whose value is in rax through a table of offsets from
+// the table to the case labels, going to `otherwise` for a value outside
+// the table or without a case.
+fn gen_jump_table(
+    cases: &[SwitchCase],
+    ty: &Type,
+    min: i128,
+    len: i128,
+    label: usize,
+    otherwise: &str,
+) {
+    let mut targets = vec![otherwise.to_string(); len as usize];
+    for (i, case) in cases.iter().enumerate() {
+        targets[(case_key(case.value, ty) - min) as usize] = format!(".Lcase{}.{}", label, i);
+    }
+    println!("  mov rdi, {}", min as i64);
+    println!("  sub rax, rdi");
+    println!("  cmp rax, {}", len - 1);
+    println!("  ja {}", otherwise);
+    println!("  lea rdi, .Ltable{}[rip]", label);
+    println!("  movsxd rax, dword ptr [rdi+rax*4]");
+    println!("  add rax, rdi");
+    println!("  jmp rax");
+    println!(".Ltable{}:", label);
+    for target in targets {
+        println!("  .long {}-.Ltable{}", target, label);
+    }
+}
+
+fn gen(node: Node, ctx: &mut Context) {
+    match node.value {
         NodeKind::Int(n) => {
-            print!("  push {}\n", n);
+            // `push` takes at most a sign-extended 32-bit immediate.
+            if n == n as i32 as i64 {
+                println!("  push {}", n);
+            } else {
+                println!("  mov rax, {}", n);
+                println!("  push rax");
+            }
         }
-        NodeKind::LocalVariable(_) => {
-            gen_lval(node);
-            print!("  pop rax\n");
-            print!("  mov rax, [rax]\n");
-            print!("  push rax\n");
+        NodeKind::Float(f) => {
+            let bits = match node.ty.unwrap() {
+                Type::Float => (f as f32).to_bits() as u64,
+                _ => f.to_bits(),
+            };
+            println!("  mov rax, {:#x}", bits);
+            println!("  push rax");
+        }
+        NodeKind::Cast(expr) => {
+            let ty = node.ty.unwrap();
+            let from = expr.ty.clone().unwrap();
+            gen(*expr, ctx);
+            if ty.is_flonum() || from.is_flonum() {
+                println!("  pop rax");
+                convert_flonum(&from, &ty, ctx);
+                println!("  push rax");
+            } else if ty.is_integer() {
+                println!("  pop rax");
+                convert(&ty);
+                println!("  push rax");
+            }
+        }
+        NodeKind::Str(bytes) => {
+            println!("  lea rax, .LC{}[rip]", ctx.add_string(bytes));
+            println!("  push rax");
+        }
+        NodeKind::LocalVariable(_) | NodeKind::GlobalVariable(_) | NodeKind::Member { .. } => {
+            let ty = node.ty.clone().unwrap();
+            gen_lval(node, ctx);
+            load(&ty);
         }
         NodeKind::Assign { lhs, rhs } => {
-            gen_lval(*lhs);
-            gen(*rhs);
-            print!("  pop rdi\n");
-            print!("  pop rax\n");
-            print!("  mov [rax], rdi\n");
-            print!("  push rdi\n");
+            let ty = lhs.ty.clone().unwrap();
+            gen_lval(*lhs, ctx);
+            gen(*rhs, ctx);
+            store(&ty);
+        }
+        NodeKind::FunCall { name, args } => {
+            // Integer and floating arguments are passed in separate registers.
+            let (mut gp, mut fp) = (0, 0);
+            let regs: Vec<_> = args
+                .iter()
+                .map(|arg| {
+                    if arg.ty.as_ref().unwrap().is_flonum() {
+                        fp += 1;
+                        ARG_XMMS[fp - 1]
+                    } else {
+                        gp += 1;
+                        ARG_REGS[gp - 1]
+                    }
+                })
+                .collect();
+            for arg in args {
+                gen(arg, ctx);
+            }
+            for reg in regs.iter().rev() {
+                if reg.starts_with("xmm") {
+                    println!("  pop rax");
+                    println!("  movq {}, rax", reg);
+                } else {
+                    println!("  pop {}", reg);
+                }
+            }
+            // The ABI requires rsp to be 16-byte aligned at the call.
+            let label = ctx.new_label();
+            println!("  mov rax, rsp");
+            println!("  and rax, 15");
+            // al holds the number of vector registers used by a variadic call.
+            println!("  jnz .Lcall{}", label);
+            println!("  mov rax, {}", fp);
+            println!("  call {}", name);
+            println!("  jmp .Lend{}", label);
+            println!(".Lcall{}:", label);
+            println!("  sub rsp, 8");
+            println!("  mov rax, {}", fp);
+            println!("  call {}", name);
+            println!("  add rsp, 8");
+            println!(".Lend{}:", label);
+            match node.ty {
+                Some(Type::Float) => println!("  movd eax, xmm0"),
+                Some(Type::Double) => println!("  movq rax, xmm0"),
+                _ => (),
+            }
+            println!("  push rax");
+        }
+        NodeKind::UniOp { op, expr } => match op.value {
+            UniOpKind::Minus => {
+                let ty = node.ty.unwrap();
+                gen(*expr, ctx);
+                println!("  pop rax");
+                if ty.is_flonum() {
+                    // Flip the sign bit.
+                    println!("  btc rax, {}", ty.size() * 8 - 1);
+                } else {
+                    println!("  neg rax");
+                    convert(&ty);
+                }
+                println!("  push rax");
+            }
+            UniOpKind::Addr => gen_lval(*expr, ctx),
+            UniOpKind::Deref => {
+                let ty = node.ty.unwrap();
+                gen(*expr, ctx);
+                load(&ty);
+            }
+            UniOpKind::Not => {
+                gen_cond(*expr, ctx);
+                println!("  sete al");
+                println!("  movzb rax, al");
+                println!("  push rax");
+            }
+            UniOpKind::BitNot => {
+                let ty = node.ty.unwrap();
+                gen(*expr, ctx);
+                println!("  pop rax");
+                println!("  not rax");
+                convert(&ty);
+                println!("  push rax");
+            }
+        },
+        // Either operand may decide the result, in which case the right
+        // one is not evaluated.
+        NodeKind::BinOp { op, lhs, rhs } if op.value == BinOpKind::LogAnd => {
+            let label = ctx.new_label();
+            gen_cond(*lhs, ctx);
+            println!("  je .Lelse{}", label);
+            gen_cond(*rhs, ctx);
+            println!("  je .Lelse{}", label);
+            println!("  push 1");
+            println!("  jmp .Lend{}", label);
+            println!(".Lelse{}:", label);
+            println!("  push 0");
+            println!(".Lend{}:", label);
+        }
+        NodeKind::BinOp { op, lhs, rhs } if op.value == BinOpKind::LogOr => {
+            let label = ctx.new_label();
+            gen_cond(*lhs, ctx);
+            println!("  jne .Lelse{}", label);
+            gen_cond(*rhs, ctx);
+            println!("  jne .Lelse{}", label);
+            println!("  push 0");
+            println!("  jmp .Lend{}", label);
+            println!(".Lelse{}:", label);
+            println!("  push 1");
+            println!(".Lend{}:", label);
         }
         NodeKind::BinOp { op, lhs, rhs } => {
-            gen(*lhs);
-            gen(*rhs);
-            print!("  pop rdi\n");
-            print!("  pop rax\n");
+            let ty = node.ty.unwrap();
+            let lhs_ty = lhs.ty.clone().unwrap();
+            let rhs_ty = rhs.ty.clone().unwrap();
+            gen(*lhs, ctx);
+            gen(*rhs, ctx);
+            println!("  pop rdi");
+            println!("  pop rax");
+            if lhs_ty.is_flonum() {
+                gen_flonum_binop(&op.value, &lhs_ty);
+                println!("  push rax");
+                return;
+            }
             match op.value {
-                BinOpKind::Add => print!("  add rax, rdi\n"),
-                BinOpKind::Sub => print!("  sub rax, rdi\n"),
-                BinOpKind::Mul => print!("  imul rax, rdi\n"),
+                BinOpKind::Add => {
+                    scale_pointer_operand(&lhs_ty, &rhs_ty);
+                    println!("  add rax, rdi");
+                }
+                BinOpKind::Sub => match (lhs_ty.base(), rhs_ty.base()) {
+                    // The difference of two pointers counts elements, not bytes.
+                    (Some(base), Some(_)) => {
+                        println!("  sub rax, rdi");
+                        println!("  mov rdi, {}", base.size());
+                        println!("  cqo");
+                        println!("  idiv rdi");
+                    }
+                    _ => {
+                        scale_pointer_operand(&lhs_ty, &rhs_ty);
+                        println!("  sub rax, rdi");
+                    }
+                },
+                BinOpKind::Mul => println!("  imul rax, rdi"),
+                BinOpKind::Quo if ty.is_unsigned() => {
+                    println!("  mov rdx, 0");
+                    println!("  div rdi");
+                }
                 BinOpKind::Quo => {
-                    print!("  cqo\n");
-                    print!("  idiv rdi\n");
+                    println!("  cqo");
+                    println!("  idiv rdi");
+                }
+                BinOpKind::Rem if ty.is_unsigned() => {
+                    println!("  mov rdx, 0");
+                    println!("  div rdi");
+                    println!("  mov rax, rdx");
+                }
+                BinOpKind::Rem => {
+                    println!("  cqo");
+                    println!("  idiv rdi");
+                    println!("  mov rax, rdx");
+                }
+                BinOpKind::BitAnd => println!("  and rax, rdi"),
+                BinOpKind::BitOr => println!("  or rax, rdi"),
+                BinOpKind::BitXor => println!("  xor rax, rdi"),
+                // The left operand is extended to 64 bits by its signedness,
+                // so shifting all of rax gives the right bits for any width.
+                BinOpKind::Shl => {
+                    println!("  mov rcx, rdi");
+                    println!("  shl rax, cl");
+                }
+                BinOpKind::Shr => {
+                    println!("  mov rcx, rdi");
+                    if lhs_ty.is_unsigned() {
+                        println!("  shr rax, cl");
+                    } else {
+                        println!("  sar rax, cl");
+                    }
                 }
                 BinOpKind::EQ => {
-                    print!("  cmp rax, rdi\n");
-                    print!("  sete al\n");
-                    print!("  movzb rax, al\n");
+                    println!("  cmp rax, rdi");
+                    println!("  sete al");
+                    println!("  movzb rax, al");
                 }
                 BinOpKind::NEQ => {
-                    print!("  cmp rax, rdi\n");
-                    print!("  setne al\n");
-                    print!("  movzb rax, al\n");
+                    println!("  cmp rax, rdi");
+                    println!("  setne al");
+                    println!("  movzb rax, al");
                 }
                 BinOpKind::LSS => {
-                    print!("  cmp rax, rdi\n");
-                    print!("  setl al\n");
-                    print!("  movzb rax, al\n");
+                    println!("  cmp rax, rdi");
+                    if lhs_ty.is_unsigned() {
+                        println!("  setb al");
+                    } else {
+                        println!("  setl al");
+                    }
+                    println!("  movzb rax, al");
                 }
                 BinOpKind::LEQ => {
-                    print!("  cmp rax, rdi\n");
-                    print!("  setle al\n");
-                    print!("  movzb rax, al\n");
+                    println!("  cmp rax, rdi");
+                    if lhs_ty.is_unsigned() {
+                        println!("  setbe al");
+                    } else {
+                        println!("  setle al");
+                    }
+                    println!("  movzb rax, al");
                 }
+                BinOpKind::LogAnd | BinOpKind::LogOr => unreachable!(),
             }
-            print!("  push rax\n");
+            // Wrap the result around to the width of its type.
+            if ty.is_integer() {
+                convert(&ty);
+            }
+            println!("  push rax");
+        }
+        NodeKind::Conditional { cond, then, els } => {
+            let label = ctx.new_label();
+            gen_cond(*cond, ctx);
+            println!("  je .Lelse{}", label);
+            gen(*then, ctx);
+            println!("  jmp .Lend{}", label);
+            println!(".Lelse{}:", label);
+            gen(*els, ctx);
+            println!(".Lend{}:", label);
         }
+        NodeKind::Comma { lhs, rhs } => {
+            gen(*lhs, ctx);
+            println!("  pop rax");
+            gen(*rhs, ctx);
+        }
+        NodeKind::Return(_)
+        | NodeKind::CompoundAssign { .. }
+        | NodeKind::If { .. }
+        | NodeKind::Block(_)
+        | NodeKind::While { .. }
+        | NodeKind::DoWhile { .. }
+        | NodeKind::For { .. }
+        | NodeKind::Switch { .. }
+        | NodeKind::Case { .. }
+        | NodeKind::Default(_)
+        | NodeKind::Label { .. }
+        | NodeKind::Goto(_)
+        | NodeKind::Break
+        | NodeKind::Continue
+        | NodeKind::Sizeof(_) => unreachable!(),
     }
 }
 
-fn gen_lval(node: Node) {
+// Computes `rax op rdi` on two floating operands of type `ty`, leaving the
+// result in rax. The unordered comparisons of a NaN are false, except `!=`.
+fn gen_flonum_binop(op: &BinOpKind, ty: &Type) {
+    let sse = sse(ty);
+    println!("  movq xmm0, rax");
+    println!("  movq xmm1, rdi");
+    match op {
+        BinOpKind::Add => println!("  add{} xmm0, xmm1", sse),
+        BinOpKind::Sub => println!("  sub{} xmm0, xmm1", sse),
+        BinOpKind::Mul => println!("  mul{} xmm0, xmm1", sse),
+        BinOpKind::Quo => println!("  div{} xmm0, xmm1", sse),
+        BinOpKind::EQ => {
+            println!("  ucomi{} xmm0, xmm1", sse);
+            println!("  sete al");
+            println!("  setnp dl");
+            println!("  and al, dl");
+        }
+        BinOpKind::NEQ => {
+            println!("  ucomi{} xmm0, xmm1", sse);
+            println!("  setne al");
+            println!("  setp dl");
+            println!("  or al, dl");
+        }
+        // `a < b` is tested as `b > a`, for which a NaN sets the carry flag.
+        BinOpKind::LSS => {
+            println!("  ucomi{} xmm1, xmm0", sse);
+            println!("  seta al");
+        }
+        BinOpKind::LEQ => {
+            println!("  ucomi{} xmm1, xmm0", sse);
+            println!("  setae al");
+        }
+        _ => unreachable!(),
+    }
+    match op {
+        BinOpKind::Add | BinOpKind::Sub | BinOpKind::Mul | BinOpKind::Quo => from_xmm0(ty),
+        _ => println!("  movzb rax, al"),
+    }
+}
+
+fn gen_lval(node: Node, ctx: &mut Context) {
     match node.value {
-        NodeKind::LocalVariable(offset) => {
-            print!("  mov rax, rbp\n");
-            print!("  sub rax, {}\n", offset);
-            print!("  push rax\n");
+        NodeKind::LocalVariable(var) => {
+            println!("  mov rax, rbp");
+            println!("  sub rax, {}", var.offset);
+            println!("  push rax");
+        }
+        NodeKind::GlobalVariable(var) => {
+            println!("  lea rax, {}[rip]", var.name);
+            println!("  push rax");
+        }
+        NodeKind::Member { expr, offset, .. } => {
+            gen_lval(*expr, ctx);
+            println!("  pop rax");
+            println!("  add rax, {}", offset);
+            println!("  push rax");
         }
+        NodeKind::UniOp {
+            op: UniOp {
+                value: UniOpKind::Deref,
+                ..
+            },
+            expr,
+        } => gen(*expr, ctx),
         _ => panic!(),
     }
 }
+
+// Multiplies the integer operand in rax or rdi by the size of the element
+// the other operand points to, so that `p + n` advances `n` elements.
+fn scale_pointer_operand(lhs: &Type, rhs: &Type) {
+    if let Some(base) = lhs.base() {
+        println!("  imul rdi, {}", base.size());
+    } else if let Some(base) = rhs.base() {
+        println!("  imul rax, {}", base.size());
+    }
+}
+
+// Truncates rax to the integer type `ty` and extends it back to 64 bits,
+// with its sign for a signed type and with zeros for an unsigned one.
+// Every integer value is kept in this form on the stack.
+fn convert(ty: &Type) {
+    match (ty.size(), ty.is_unsigned()) {
+        (1, false) => println!("  movsx rax, al"),
+        (1, true) => println!("  movzx rax, al"),
+        (2, false) => println!("  movsx rax, ax"),
+        (2, true) => println!("  movzx rax, ax"),
+        (4, false) => println!("  movsxd rax, eax"),
+        (4, true) => println!("  mov eax, eax"),
+        _ => (),
+    }
+}
+
+// Converts the value in rax from `from` to `to`, at least one of which is a
+// floating type.
+fn convert_flonum(from: &Type, to: &Type, ctx: &mut Context) {
+    if from.is_flonum() && to.is_flonum() {
+        if from != to {
+            println!("  movq xmm0, rax");
+            println!("  cvt{}2{} xmm0, xmm0", sse(from), sse(to));
+            from_xmm0(to);
+        }
+        return;
+    }
+    if to.is_flonum() {
+        let cvt = format!("cvtsi2{}", sse(to));
+        if *from == Type::ULong {
+            // Values above the signed range are halved for the conversion
+            // and doubled back, keeping the lowest bit for the rounding.
+            let label = ctx.new_label();
+            println!("  test rax, rax");
+            println!("  js .Lelse{}", label);
+            println!("  {} xmm0, rax", cvt);
+            println!("  jmp .Lend{}", label);
+            println!(".Lelse{}:", label);
+            println!("  mov rdi, rax");
+            println!("  and rdi, 1");
+            println!("  shr rax, 1");
+            println!("  or rax, rdi");
+            println!("  {} xmm0, rax", cvt);
+            println!("  add{} xmm0, xmm0", sse(to));
+            println!(".Lend{}:", label);
+        } else {
+            // Integers are kept extended to 64 bits, so any of them fits.
+            println!("  {} xmm0, rax", cvt);
+        }
+        from_xmm0(to);
+        return;
+    }
+    println!("  movq xmm0, rax");
+    if *from == Type::Float {
+        println!("  cvtss2sd xmm0, xmm0");
+    }
+    if *to == Type::ULong {
+        // Values of 2^63 and above are brought into the signed range first.
+        let label = ctx.new_label();
+        println!("  mov rax, {:#x}", ((1u64 << 63) as f64).to_bits());
+        println!("  movq xmm1, rax");
+        println!("  ucomisd xmm0, xmm1");
+        println!("  jae .Lelse{}", label);
+        println!("  cvttsd2si rax, xmm0");
+        println!("  jmp .Lend{}", label);
+        println!(".Lelse{}:", label);
+        println!("  subsd xmm0, xmm1");
+        println!("  cvttsd2si rax, xmm0");
+        println!("  btc rax, 63");
+        println!(".Lend{}:", label);
+    } else {
+        println!("  cvttsd2si rax, xmm0");
+        convert(to);
+    }
+}
+
+// Moves a floating value of type `ty` from xmm0 to rax. A float occupies
+// the low half of rax, and the rest is zero.
+fn from_xmm0(ty: &Type) {
+    match ty {
+        Type::Float => println!("  movd eax, xmm0"),
+        _ => println!("  movq rax, xmm0"),
+    }
+}
+
+// The suffix of the SSE instructions that operate on the floating type `ty`.
+fn sse(ty: &Type) -> &'static str {
+    match ty {
+        Type::Float => "ss",
+        _ => "sd",
+    }
+}
+
+// Replaces the address on top of the stack with the value it points to.
+// An array is left as its address, which is how it decays to a pointer,
+// and so is a struct, which is copied from there by `store`.
+fn load(ty: &Type) {
+    if let Type::Array(..) | Type::Struct(_) = ty {
+        return;
+    }
+    println!("  pop rax");
+    // A float is loaded like an unsigned int, which keeps the rest of rax zero.
+    match (ty.size(), ty.is_unsigned() || ty.is_flonum()) {
+        (1, false) => println!("  movsx rax, byte ptr [rax]"),
+        (1, true) => println!("  movzx rax, byte ptr [rax]"),
+        (2, false) => println!("  movsx rax, word ptr [rax]"),
+        (2, true) => println!("  movzx rax, word ptr [rax]"),
+        (4, false) => println!("  movsxd rax, dword ptr [rax]"),
+        (4, true) => println!("  mov eax, dword ptr [rax]"),
+        _ => println!("  mov rax, [rax]"),
+    }
+    println!("  push rax");
+}
+
+// Pops a value and an address, stores the value there and pushes it back.
+// A struct value is its address, and its bytes are copied one at a time.
+fn store(ty: &Type) {
+    println!("  pop rdi");
+    println!("  pop rax");
+    if let Type::Struct(_) = ty {
+        for i in 0..ty.size() {
+            println!("  mov r8b, [rdi+{}]", i);
+            println!("  mov [rax+{}], r8b", i);
+        }
+        println!("  push rdi");
+        return;
+    }
+    match ty.size() {
+        1 => println!("  mov [rax], dil"),
+        2 => println!("  mov [rax], di"),
+        4 => println!("  mov [rax], edi"),
+        _ => println!("  mov [rax], rdi"),
+    }
+    println!("  push rdi");
+}
@@ -1,26 +1,104 @@
+use super::types::Type;
+
 #[derive(Debug, PartialEq, Clone)]
 pub enum TokenKind {
-    Ident(String), // main
-    Int(i32),      // 1
-    Return,        // return
-    Eof,           // ;
-    Add,           // +
-    Sub,           // -
-    Mul,           // *
-    Quo,           // /
-    LParen,        // (
-    RParen,        // )
-    ASSIGN,        // =
-    EQ,            // ==
-    NEQ,           // !=
-    LEQ,           // <=
-    GEQ,           // >=
-    LSS,           // <
-    GRT,           // >
+    Ident(String),    // main
+    Int(i64, Type),   // 1, 0x1fUL
+    Float(f64, Type), // 1.5, .5e-3f
+    Str(Vec<u8>),     // "abc"
+    Return,           // return
+    Sizeof,           // sizeof
+    Alignof,          // _Alignof
+    IntType,          // int
+    CharType,         // char
+    ShortType,        // short
+    FloatType,        // float
+    DoubleType,       // double
+    LongType,         // long
+    Signed,           // signed
+    Unsigned,         // unsigned
+    Struct,           // struct
+    Union,            // union
+    Typedef,          // typedef
+    Enum,             // enum
+    Const,            // const
+    Volatile,         // volatile
+    Restrict,         // restrict
+    Static,           // static
+    Extern,           // extern
+    Inline,           // inline
+    If,               // if
+    Else,             // else
+    While,            // while
+    Do,               // do
+    For,              // for
+    Break,            // break
+    Continue,         // continue
+    Switch,           // switch
+    Case,             // case
+    Default,          // default
+    Goto,             // goto
+    Auto,             // auto
+    Register,         // register
+    Void,             // void
+    Bool,             // _Bool
+    Complex,          // _Complex
+    Imaginary,        // _Imaginary
+    Alignas,          // _Alignas
+    Atomic,           // _Atomic
+    Generic,          // _Generic
+    Noreturn,         // _Noreturn
+    StaticAssert,     // _Static_assert
+    ThreadLocal,      // _Thread_local
+    Eof,              // ;
+    Add,              // +
+    Sub,              // -
+    Mul,              // *
+    Quo,              // /
+    Rem,              // %
+    Amp,              // &
+    Or,               // |
+    Xor,              // ^
+    Tilde,            // ~
+    Shl,              // <<
+    Shr,              // >>
+    LAnd,             // &&
+    LOr,              // ||
+    Not,              // !
+    Question,         // ?
+    Colon,            // :
+    LParen,           // (
+    RParen,           // )
+    LBrace,           // {
+    RBrace,           // }
+    LBracket,         // [
+    RBracket,         // ]
+    Comma,            // ,
+    Dot,              // .
+    Arrow,            // ->
+    ASSIGN,           // =
+    AddAssign,        // +=
+    SubAssign,        // -=
+    MulAssign,        // *=
+    QuoAssign,        // /=
+    RemAssign,        // %=
+    AndAssign,        // &=
+    OrAssign,         // |=
+    XorAssign,        // ^=
+    ShlAssign,        // <<=
+    ShrAssign,        // >>=
+    Inc,              // ++
+    Dec,              // --
+    EQ,               // ==
+    NEQ,              // !=
+    LEQ,              // <=
+    GEQ,              // >=
+    LSS,              // <
+    GRT,              // >
 }
 
 #[derive(Debug, PartialEq, Copy, Clone)]
-pub struct Loc(usize, usize);
+pub struct Loc(pub usize, pub usize);
 
 impl Loc {
     pub fn merge(&self, other: &Self) -> Loc {
@@ -29,7 +107,7 @@ impl Loc {
     }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
 pub struct Annot<T> {
     pub value: T,
     pub loc: Loc,
@@ -47,11 +125,18 @@ impl Token {
     fn ident(s: &str, loc: Loc) -> Self {
         Self::new(TokenKind::Ident(s.to_string()), loc)
     }
-    fn int(n: i32, loc: Loc) -> Self {
-        Self::new(TokenKind::Int(n), loc)
+    #[cfg(test)]
+    fn int(n: i64, loc: Loc) -> Self {
+        Self::new(TokenKind::Int(n, Type::Int), loc)
+    }
+    fn int_of(n: i64, ty: Type, loc: Loc) -> Self {
+        Self::new(TokenKind::Int(n, ty), loc)
+    }
+    fn float(f: f64, ty: Type, loc: Loc) -> Self {
+        Self::new(TokenKind::Float(f, ty), loc)
     }
-    fn return_(loc: Loc) -> Self {
-        Self::new(TokenKind::Return, loc)
+    fn str(bytes: &[u8], loc: Loc) -> Self {
+        Self::new(TokenKind::Str(bytes.to_vec()), loc)
     }
     fn eof(loc: Loc) -> Self {
         Self::new(TokenKind::Eof, loc)
@@ -68,15 +153,78 @@ impl Token {
     fn quo(loc: Loc) -> Self {
         Self::new(TokenKind::Quo, loc)
     }
+    fn rem(loc: Loc) -> Self {
+        Self::new(TokenKind::Rem, loc)
+    }
+    fn amp(loc: Loc) -> Self {
+        Self::new(TokenKind::Amp, loc)
+    }
+    fn or(loc: Loc) -> Self {
+        Self::new(TokenKind::Or, loc)
+    }
+    fn xor(loc: Loc) -> Self {
+        Self::new(TokenKind::Xor, loc)
+    }
+    fn tilde(loc: Loc) -> Self {
+        Self::new(TokenKind::Tilde, loc)
+    }
+    fn shl(loc: Loc) -> Self {
+        Self::new(TokenKind::Shl, loc)
+    }
+    fn shr(loc: Loc) -> Self {
+        Self::new(TokenKind::Shr, loc)
+    }
+    fn land(loc: Loc) -> Self {
+        Self::new(TokenKind::LAnd, loc)
+    }
+    fn lor(loc: Loc) -> Self {
+        Self::new(TokenKind::LOr, loc)
+    }
+    fn not(loc: Loc) -> Self {
+        Self::new(TokenKind::Not, loc)
+    }
+    fn question(loc: Loc) -> Self {
+        Self::new(TokenKind::Question, loc)
+    }
+    fn colon(loc: Loc) -> Self {
+        Self::new(TokenKind::Colon, loc)
+    }
     fn lparen(loc: Loc) -> Self {
         Self::new(TokenKind::LParen, loc)
     }
     fn rparen(loc: Loc) -> Self {
         Self::new(TokenKind::RParen, loc)
     }
+    fn lbrace(loc: Loc) -> Self {
+        Self::new(TokenKind::LBrace, loc)
+    }
+    fn rbrace(loc: Loc) -> Self {
+        Self::new(TokenKind::RBrace, loc)
+    }
+    fn lbracket(loc: Loc) -> Self {
+        Self::new(TokenKind::LBracket, loc)
+    }
+    fn rbracket(loc: Loc) -> Self {
+        Self::new(TokenKind::RBracket, loc)
+    }
+    fn comma(loc: Loc) -> Self {
+        Self::new(TokenKind::Comma, loc)
+    }
+    fn dot(loc: Loc) -> Self {
+        Self::new(TokenKind::Dot, loc)
+    }
+    fn arrow(loc: Loc) -> Self {
+        Self::new(TokenKind::Arrow, loc)
+    }
     fn assign(loc: Loc) -> Self {
         Self::new(TokenKind::ASSIGN, loc)
     }
+    fn inc(loc: Loc) -> Self {
+        Self::new(TokenKind::Inc, loc)
+    }
+    fn dec(loc: Loc) -> Self {
+        Self::new(TokenKind::Dec, loc)
+    }
     fn eq(loc: Loc) -> Self {
         Self::new(TokenKind::EQ, loc)
     }
@@ -98,111 +246,436 @@ impl Token {
 }
 
 fn is_number(c: char) -> bool {
-    c.to_digit(10).is_some()
+    c.is_ascii_digit()
 }
 
 fn is_identifier_nameable(c: char) -> bool {
     c.is_ascii_alphanumeric() || c == '_'
 }
 
-fn is_match(input: &Vec<char>, pos: usize, expected: &str) -> bool {
+// The keywords of C11. A word is looked up here only once it has been read
+// in full, so that `returned` is an identifier rather than `return` and `ed`.
+const KEYWORDS: &[(&str, TokenKind)] = &[
+    ("auto", TokenKind::Auto),
+    ("break", TokenKind::Break),
+    ("case", TokenKind::Case),
+    ("char", TokenKind::CharType),
+    ("const", TokenKind::Const),
+    ("continue", TokenKind::Continue),
+    ("default", TokenKind::Default),
+    ("do", TokenKind::Do),
+    ("double", TokenKind::DoubleType),
+    ("else", TokenKind::Else),
+    ("enum", TokenKind::Enum),
+    ("extern", TokenKind::Extern),
+    ("float", TokenKind::FloatType),
+    ("for", TokenKind::For),
+    ("goto", TokenKind::Goto),
+    ("if", TokenKind::If),
+    ("inline", TokenKind::Inline),
+    ("int", TokenKind::IntType),
+    ("long", TokenKind::LongType),
+    ("register", TokenKind::Register),
+    ("restrict", TokenKind::Restrict),
+    ("return", TokenKind::Return),
+    ("short", TokenKind::ShortType),
+    ("signed", TokenKind::Signed),
+    ("sizeof", TokenKind::Sizeof),
+    ("static", TokenKind::Static),
+    ("struct", TokenKind::Struct),
+    ("switch", TokenKind::Switch),
+    ("typedef", TokenKind::Typedef),
+    ("union", TokenKind::Union),
+    ("unsigned", TokenKind::Unsigned),
+    ("void", TokenKind::Void),
+    ("volatile", TokenKind::Volatile),
+    ("while", TokenKind::While),
+    ("_Alignas", TokenKind::Alignas),
+    ("_Alignof", TokenKind::Alignof),
+    ("_Atomic", TokenKind::Atomic),
+    ("_Bool", TokenKind::Bool),
+    ("_Complex", TokenKind::Complex),
+    ("_Generic", TokenKind::Generic),
+    ("_Imaginary", TokenKind::Imaginary),
+    ("_Noreturn", TokenKind::Noreturn),
+    ("_Static_assert", TokenKind::StaticAssert),
+    ("_Thread_local", TokenKind::ThreadLocal),
+];
+
+fn is_match(input: &[char], pos: usize, expected: &str) -> bool {
     let end = pos + expected.len();
-    if input.len() <= pos || input.len() <= end {
+    if input.len() < end {
         return false;
     }
-    let input_str: String = input[pos..(end)].into_iter().collect();
+    let input_str: String = input[pos..(end)].iter().collect();
     input_str == expected
 }
 
-fn consume(input: &Vec<char>, pos: usize, expected: &str) -> Result<(String, usize), LexError> {
+fn consume(input: &[char], pos: usize, expected: &str) -> Result<(String, usize), LexError> {
     if input.len() <= pos {
         return Err(LexError::eof(Loc(pos, pos)));
     }
     let end = pos + expected.len();
-    let input_str: String = input[pos..(end)].into_iter().collect();
-    if input_str != expected {
-        return Err(LexError::invalid_char(input[pos], Loc(pos, end)));
+    if !input[pos..]
+        .iter()
+        .copied()
+        .take(expected.len())
+        .eq(expected.chars())
+    {
+        return Err(LexError::invalid_char(
+            input[pos],
+            Loc(pos, end.min(input.len())),
+        ));
     }
     Ok((expected.to_string(), end))
 }
 
-fn lex_int(input: &Vec<char>, mut pos: usize) -> (Token, usize) {
-    let start = pos;
-    while pos < input.len() && is_number(input[pos]) {
-        pos += 1;
+// Reads a number starting with a digit, or with a dot followed by a digit.
+// The whole run of characters that may continue a number is read first, so
+// that `1abc` is a malformed number rather than a number and an identifier.
+fn lex_number(input: &[char], start: usize) -> Result<(Token, usize), LexError> {
+    let mut end = start;
+    while end < input.len() {
+        let c = input[end];
+        let exponent_sign = (c == '+' || c == '-') && matches!(input[end - 1], 'e' | 'E');
+        if !is_identifier_nameable(c) && c != '.' && !exponent_sign {
+            break;
+        }
+        end += 1;
     }
-    let n_str: String = input[start..pos].into_iter().collect();
-    let n: i32 = n_str.parse().unwrap();
-    (Token::int(n, Loc(start, pos)), pos)
+    let text: String = input[start..end].iter().collect();
+    let loc = Loc(start, end);
+    let lower = text.to_ascii_lowercase();
+    let is_hex = lower.starts_with("0x");
+    let tok = if lower.contains('.') || (!is_hex && lower.contains('e')) {
+        lex_float(&text, loc)?
+    } else {
+        lex_int(&text, loc)?
+    };
+    Ok((tok, end))
 }
 
-fn lex_identifier(input: &Vec<char>, mut pos: usize) -> (Token, usize) {
+// Reads a decimal floating-point literal with an optional `f` or `l` suffix.
+// A `long double` is represented as a double.
+fn lex_float(text: &str, loc: Loc) -> Result<Token, LexError> {
+    let (digits, ty) = match text.chars().last() {
+        Some('f') | Some('F') => (&text[..text.len() - 1], Type::Float),
+        Some('l') | Some('L') => (&text[..text.len() - 1], Type::Double),
+        _ => (text, Type::Double),
+    };
+    // Rust also accepts `inf` and `nan`, which C spells differently.
+    if !digits.starts_with(|c: char| c == '.' || c.is_ascii_digit()) {
+        return Err(LexError::invalid_number(loc));
+    }
+    match digits.parse::<f64>() {
+        Ok(f) => Ok(Token::float(f, ty, loc)),
+        Err(_) => Err(LexError::invalid_number(loc)),
+    }
+}
+
+// Reads an integer literal: decimal, `0x` hex, `0b` binary or `0` octal,
+// optionally followed by a `u`, `l` or `ll` suffix in either order.
+fn lex_int(text: &str, loc: Loc) -> Result<Token, LexError> {
+    let lower = text.to_ascii_lowercase();
+    let (radix, digits) = if lower.starts_with("0x") {
+        (16, &text[2..])
+    } else if lower.starts_with("0b") {
+        (2, &text[2..])
+    } else if text.starts_with('0') {
+        (8, text)
+    } else {
+        (10, text)
+    };
+    let len = digits
+        .find(|c: char| !c.is_digit(radix))
+        .unwrap_or(digits.len());
+    let (digits, suffix) = digits.split_at(len);
+    if digits.is_empty() {
+        return Err(LexError::invalid_number(loc));
+    }
+    let n = match u64::from_str_radix(digits, radix) {
+        Ok(n) => n,
+        Err(_) => return Err(LexError::invalid_number(loc)),
+    };
+    let (unsigned, long) = match &suffix.to_ascii_lowercase()[..] {
+        "" => (false, false),
+        "u" => (true, false),
+        "l" | "ll" => (false, true),
+        "ul" | "lu" | "ull" | "llu" => (true, true),
+        _ => return Err(LexError::invalid_number(loc)),
+    };
+    // The first type that can represent the value. A decimal literal without
+    // a `u` suffix never becomes unsigned.
+    let decimal = radix == 10;
+    let fits_int = n <= i32::MAX as u64;
+    let fits_uint = n <= u32::MAX as u64;
+    let fits_long = n <= i64::MAX as u64;
+    let ty = match (unsigned, long) {
+        (false, false) if fits_int => Type::Int,
+        (false, false) if fits_uint && !decimal => Type::UInt,
+        (true, false) if fits_uint => Type::UInt,
+        (false, _) if fits_long => Type::Long,
+        (false, _) if decimal => return Err(LexError::invalid_number(loc)),
+        _ => Type::ULong,
+    };
+    Ok(Token::int_of(n as i64, ty, loc))
+}
+
+// Reads an identifier, which is a keyword if the whole of it is one.
+fn lex_identifier(input: &[char], mut pos: usize) -> (Token, usize) {
     let start = pos;
     while pos < input.len() && is_identifier_nameable(input[pos]) {
         pos += 1;
     }
-    let n_str: String = input[start..pos].into_iter().collect();
-    (Token::ident(&n_str, Loc(start, pos)), pos)
+    let n_str: String = input[start..pos].iter().collect();
+    let loc = Loc(start, pos);
+    let tok = match KEYWORDS.iter().find(|(name, _)| *name == n_str) {
+        Some((_, kind)) => Token::new(kind.clone(), loc),
+        None => Token::ident(&n_str, loc),
+    };
+    (tok, pos)
 }
 
-fn lex_add(input: &Vec<char>, start: usize) -> Result<(Token, usize), LexError> {
-    consume(input, start, "+").map(|(_, end)| (Token::add(Loc(start, end)), end))
+fn lex_str(input: &[char], start: usize) -> Result<(Token, usize), LexError> {
+    let mut pos = start + 1;
+    let mut bytes = Vec::<u8>::new();
+    loop {
+        match input.get(pos) {
+            None | Some('\n') => return Err(LexError::unclosed_str(Loc(start, pos))),
+            Some('"') => break,
+            Some('\\') => {
+                let (c, p) = lex_escape(input, pos + 1)?;
+                bytes.push(c);
+                pos = p;
+            }
+            Some(&c) => {
+                let mut buf = [0; 4];
+                bytes.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+                pos += 1;
+            }
+        }
+    }
+    Ok((Token::str(&bytes, Loc(start, pos + 1)), pos + 1))
 }
-fn lex_return(input: &Vec<char>, start: usize) -> Result<(Token, usize), LexError> {
-    consume(input, start, "return").map(|(_, end)| (Token::return_(Loc(start, end)), end))
+
+// Skips a `//` comment up to, but not including, the end of its line.
+fn skip_line_comment(input: &[char], mut pos: usize) -> usize {
+    while pos < input.len() && input[pos] != '\n' {
+        pos += 1;
+    }
+    pos
 }
-fn lex_eof(input: &Vec<char>, start: usize) -> Result<(Token, usize), LexError> {
+
+// Skips a `/*` comment, which may span lines but does not nest.
+fn skip_block_comment(input: &[char], start: usize) -> Result<usize, LexError> {
+    let mut pos = start + 2;
+    while pos + 1 < input.len() {
+        if input[pos] == '*' && input[pos + 1] == '/' {
+            return Ok(pos + 2);
+        }
+        pos += 1;
+    }
+    Err(LexError::unclosed_comment(Loc(start, start + 2)))
+}
+
+// Reads the escape sequence following a backslash at `pos - 1`.
+fn lex_escape(input: &[char], mut pos: usize) -> Result<(u8, usize), LexError> {
+    let c = match input.get(pos) {
+        Some(&c) => c,
+        None => return Err(LexError::eof(Loc(pos, pos))),
+    };
+    if c.is_digit(8) {
+        let mut n = 0u32;
+        let start = pos;
+        while pos < input.len() && pos < start + 3 && input[pos].is_digit(8) {
+            n = n * 8 + input[pos].to_digit(8).unwrap();
+            pos += 1;
+        }
+        return Ok((n as u8, pos));
+    }
+    if c == 'x' {
+        let start = pos + 1;
+        pos = start;
+        let mut n = 0u32;
+        while pos < input.len() && input[pos].is_ascii_hexdigit() {
+            n = n
+                .wrapping_mul(16)
+                .wrapping_add(input[pos].to_digit(16).unwrap());
+            pos += 1;
+        }
+        if pos == start {
+            return Err(LexError::invalid_char(c, Loc(start - 1, start)));
+        }
+        return Ok((n as u8, pos));
+    }
+    let b = match c {
+        'a' => 7,
+        'b' => 8,
+        't' => b'\t',
+        'n' => b'\n',
+        'v' => 11,
+        'f' => 12,
+        'r' => b'\r',
+        // [GNU] \e is the ASCII escape character.
+        'e' => 27,
+        c if c.is_ascii() => c as u8,
+        c => return Err(LexError::invalid_char(c, Loc(pos, pos + 1))),
+    };
+    Ok((b, pos + 1))
+}
+
+fn lex_add(input: &[char], start: usize) -> Result<(Token, usize), LexError> {
+    consume(input, start, "+").map(|(_, end)| (Token::add(Loc(start, end)), end))
+}
+fn lex_eof(input: &[char], start: usize) -> Result<(Token, usize), LexError> {
     consume(input, start, ";").map(|(_, end)| (Token::eof(Loc(start, end)), end))
 }
-fn lex_sub(input: &Vec<char>, start: usize) -> Result<(Token, usize), LexError> {
+fn lex_sub(input: &[char], start: usize) -> Result<(Token, usize), LexError> {
     consume(input, start, "-").map(|(_, end)| (Token::sub(Loc(start, end)), end))
 }
-fn lex_mul(input: &Vec<char>, start: usize) -> Result<(Token, usize), LexError> {
+fn lex_mul(input: &[char], start: usize) -> Result<(Token, usize), LexError> {
     consume(input, start, "*").map(|(_, end)| (Token::mul(Loc(start, end)), end))
 }
-fn lex_quo(input: &Vec<char>, start: usize) -> Result<(Token, usize), LexError> {
+fn lex_quo(input: &[char], start: usize) -> Result<(Token, usize), LexError> {
     consume(input, start, "/").map(|(_, end)| (Token::quo(Loc(start, end)), end))
 }
-fn lex_lparen(input: &Vec<char>, start: usize) -> Result<(Token, usize), LexError> {
+fn lex_rem(input: &[char], start: usize) -> Result<(Token, usize), LexError> {
+    consume(input, start, "%").map(|(_, end)| (Token::rem(Loc(start, end)), end))
+}
+fn lex_amp(input: &[char], start: usize) -> Result<(Token, usize), LexError> {
+    consume(input, start, "&").map(|(_, end)| (Token::amp(Loc(start, end)), end))
+}
+fn lex_or(input: &[char], start: usize) -> Result<(Token, usize), LexError> {
+    consume(input, start, "|").map(|(_, end)| (Token::or(Loc(start, end)), end))
+}
+fn lex_xor(input: &[char], start: usize) -> Result<(Token, usize), LexError> {
+    consume(input, start, "^").map(|(_, end)| (Token::xor(Loc(start, end)), end))
+}
+fn lex_tilde(input: &[char], start: usize) -> Result<(Token, usize), LexError> {
+    consume(input, start, "~").map(|(_, end)| (Token::tilde(Loc(start, end)), end))
+}
+fn lex_shl(input: &[char], start: usize) -> Result<(Token, usize), LexError> {
+    consume(input, start, "<<").map(|(_, end)| (Token::shl(Loc(start, end)), end))
+}
+fn lex_shr(input: &[char], start: usize) -> Result<(Token, usize), LexError> {
+    consume(input, start, ">>").map(|(_, end)| (Token::shr(Loc(start, end)), end))
+}
+fn lex_land(input: &[char], start: usize) -> Result<(Token, usize), LexError> {
+    consume(input, start, "&&").map(|(_, end)| (Token::land(Loc(start, end)), end))
+}
+fn lex_lor(input: &[char], start: usize) -> Result<(Token, usize), LexError> {
+    consume(input, start, "||").map(|(_, end)| (Token::lor(Loc(start, end)), end))
+}
+fn lex_not(input: &[char], start: usize) -> Result<(Token, usize), LexError> {
+    consume(input, start, "!").map(|(_, end)| (Token::not(Loc(start, end)), end))
+}
+fn lex_question(input: &[char], start: usize) -> Result<(Token, usize), LexError> {
+    consume(input, start, "?").map(|(_, end)| (Token::question(Loc(start, end)), end))
+}
+fn lex_colon(input: &[char], start: usize) -> Result<(Token, usize), LexError> {
+    consume(input, start, ":").map(|(_, end)| (Token::colon(Loc(start, end)), end))
+}
+fn lex_lparen(input: &[char], start: usize) -> Result<(Token, usize), LexError> {
     consume(input, start, "(").map(|(_, end)| (Token::lparen(Loc(start, end)), end))
 }
-fn lex_rparen(input: &Vec<char>, start: usize) -> Result<(Token, usize), LexError> {
+fn lex_rparen(input: &[char], start: usize) -> Result<(Token, usize), LexError> {
     consume(input, start, ")").map(|(_, end)| (Token::rparen(Loc(start, end)), end))
 }
-fn lex_eq(input: &Vec<char>, start: usize) -> Result<(Token, usize), LexError> {
+fn lex_lbrace(input: &[char], start: usize) -> Result<(Token, usize), LexError> {
+    consume(input, start, "{").map(|(_, end)| (Token::lbrace(Loc(start, end)), end))
+}
+fn lex_rbrace(input: &[char], start: usize) -> Result<(Token, usize), LexError> {
+    consume(input, start, "}").map(|(_, end)| (Token::rbrace(Loc(start, end)), end))
+}
+fn lex_lbracket(input: &[char], start: usize) -> Result<(Token, usize), LexError> {
+    consume(input, start, "[").map(|(_, end)| (Token::lbracket(Loc(start, end)), end))
+}
+fn lex_rbracket(input: &[char], start: usize) -> Result<(Token, usize), LexError> {
+    consume(input, start, "]").map(|(_, end)| (Token::rbracket(Loc(start, end)), end))
+}
+fn lex_comma(input: &[char], start: usize) -> Result<(Token, usize), LexError> {
+    consume(input, start, ",").map(|(_, end)| (Token::comma(Loc(start, end)), end))
+}
+fn lex_dot(input: &[char], start: usize) -> Result<(Token, usize), LexError> {
+    consume(input, start, ".").map(|(_, end)| (Token::dot(Loc(start, end)), end))
+}
+fn lex_arrow(input: &[char], start: usize) -> Result<(Token, usize), LexError> {
+    consume(input, start, "->").map(|(_, end)| (Token::arrow(Loc(start, end)), end))
+}
+fn lex_inc(input: &[char], start: usize) -> Result<(Token, usize), LexError> {
+    consume(input, start, "++").map(|(_, end)| (Token::inc(Loc(start, end)), end))
+}
+fn lex_dec(input: &[char], start: usize) -> Result<(Token, usize), LexError> {
+    consume(input, start, "--").map(|(_, end)| (Token::dec(Loc(start, end)), end))
+}
+// Reads an operator followed by `=`, such as `+=` or `<<=`.
+fn lex_compound_assign(input: &[char], start: usize) -> Result<(Token, usize), LexError> {
+    let (op, kind) = [
+        ("+=", TokenKind::AddAssign),
+        ("-=", TokenKind::SubAssign),
+        ("*=", TokenKind::MulAssign),
+        ("/=", TokenKind::QuoAssign),
+        ("%=", TokenKind::RemAssign),
+        ("&=", TokenKind::AndAssign),
+        ("|=", TokenKind::OrAssign),
+        ("^=", TokenKind::XorAssign),
+        ("<<=", TokenKind::ShlAssign),
+        (">>=", TokenKind::ShrAssign),
+    ]
+    .iter()
+    .find(|(op, _)| input[start..].iter().copied().take(op.len()).eq(op.chars()))
+    .cloned()
+    .unwrap();
+    consume(input, start, op).map(|(_, end)| (Token::new(kind, Loc(start, end)), end))
+}
+fn lex_eq(input: &[char], start: usize) -> Result<(Token, usize), LexError> {
     consume(input, start, "==").map(|(_, end)| (Token::eq(Loc(start, end)), end))
 }
-fn lex_neq(input: &Vec<char>, start: usize) -> Result<(Token, usize), LexError> {
+fn lex_neq(input: &[char], start: usize) -> Result<(Token, usize), LexError> {
     consume(input, start, "!=").map(|(_, end)| (Token::neq(Loc(start, end)), end))
 }
-fn lex_leq(input: &Vec<char>, start: usize) -> Result<(Token, usize), LexError> {
+fn lex_leq(input: &[char], start: usize) -> Result<(Token, usize), LexError> {
     consume(input, start, "<=").map(|(_, end)| (Token::leq(Loc(start, end)), end))
 }
-fn lex_geq(input: &Vec<char>, start: usize) -> Result<(Token, usize), LexError> {
+fn lex_geq(input: &[char], start: usize) -> Result<(Token, usize), LexError> {
     consume(input, start, ">=").map(|(_, end)| (Token::geq(Loc(start, end)), end))
 }
-fn lex_assign(input: &Vec<char>, start: usize) -> Result<(Token, usize), LexError> {
+fn lex_assign(input: &[char], start: usize) -> Result<(Token, usize), LexError> {
     consume(input, start, "=").map(|(_, end)| (Token::assign(Loc(start, end)), end))
 }
-fn lex_lss(input: &Vec<char>, start: usize) -> Result<(Token, usize), LexError> {
+fn lex_lss(input: &[char], start: usize) -> Result<(Token, usize), LexError> {
     consume(input, start, "<").map(|(_, end)| (Token::lss(Loc(start, end)), end))
 }
-fn lex_grt(input: &Vec<char>, start: usize) -> Result<(Token, usize), LexError> {
+fn lex_grt(input: &[char], start: usize) -> Result<(Token, usize), LexError> {
     consume(input, start, ">").map(|(_, end)| (Token::grt(Loc(start, end)), end))
 }
 
 #[derive(Debug)]
 pub enum LexErrorKind {
     InvalidChar(char),
+    // A malformed integer literal, or one too large for any integer type.
+    InvalidNumber,
+    UnclosedStr,
+    // A `/*` with no `*/` after it, located at the `/*`.
+    UnclosedComment,
     Eof,
 }
 
-type LexError = Annot<LexErrorKind>;
+pub type LexError = Annot<LexErrorKind>;
 
 impl LexError {
     fn invalid_char(c: char, loc: Loc) -> Self {
         LexError::new(LexErrorKind::InvalidChar(c), loc)
     }
+    fn invalid_number(loc: Loc) -> Self {
+        LexError::new(LexErrorKind::InvalidNumber, loc)
+    }
+    fn unclosed_str(loc: Loc) -> Self {
+        LexError::new(LexErrorKind::UnclosedStr, loc)
+    }
+    fn unclosed_comment(loc: Loc) -> Self {
+        LexError::new(LexErrorKind::UnclosedComment, loc)
+    }
     fn eof(loc: Loc) -> Self {
         LexError::new(LexErrorKind::Eof, loc)
     }
@@ -224,33 +697,56 @@ pub fn lex(input: &str) -> Result<Vec<Token>, LexError> {
     while pos < input.len() {
         let c = input[pos];
         match c {
-            ' ' | '\n' => pos += 1,
-            c if is_number(c) => lex_a_token!(lex_int(&input, pos)),
-            c if is_identifier_nameable(c) => {
-                if is_match(&input, pos, "return") {
-                    lex_a_token!(lex_return(&input, pos)?)
-                } else {
-                    lex_a_token!(lex_identifier(&input, pos))
-                }
+            ' ' | '\t' | '\n' | '\r' | '\x0b' | '\x0c' => pos += 1,
+            '/' if input.get(pos + 1) == Some(&'/') => pos = skip_line_comment(&input, pos),
+            '/' if input.get(pos + 1) == Some(&'*') => pos = skip_block_comment(&input, pos)?,
+            c if is_number(c) => lex_a_token!(lex_number(&input, pos)?),
+            '.' if matches!(input.get(pos + 1), Some(&c) if is_number(c)) => {
+                lex_a_token!(lex_number(&input, pos)?)
+            }
+            c if is_identifier_nameable(c) => lex_a_token!(lex_identifier(&input, pos)),
+            '+' | '-' | '*' | '/' | '%' | '&' | '|' | '^' if input.get(pos + 1) == Some(&'=') => {
+                lex_a_token!(lex_compound_assign(&input, pos)?)
             }
-            '=' | '<' | '>' | '!' if input[pos + 1] == '=' => match c {
-                '=' => lex_a_token!(lex_eq(&input, pos)?),
-                '<' => lex_a_token!(lex_leq(&input, pos)?),
-                '>' => lex_a_token!(lex_geq(&input, pos)?),
-                '!' => lex_a_token!(lex_neq(&input, pos)?),
-                _ => unimplemented!(),
-            },
+            '<' if is_match(&input, pos, "<<=") => lex_a_token!(lex_compound_assign(&input, pos)?),
+            '>' if is_match(&input, pos, ">>=") => lex_a_token!(lex_compound_assign(&input, pos)?),
+            '=' if input.get(pos + 1) == Some(&'=') => lex_a_token!(lex_eq(&input, pos)?),
+            '<' if input.get(pos + 1) == Some(&'=') => lex_a_token!(lex_leq(&input, pos)?),
+            '>' if input.get(pos + 1) == Some(&'=') => lex_a_token!(lex_geq(&input, pos)?),
+            '!' if input.get(pos + 1) == Some(&'=') => lex_a_token!(lex_neq(&input, pos)?),
+            '"' => lex_a_token!(lex_str(&input, pos)?),
+            '+' if is_match(&input, pos, "++") => lex_a_token!(lex_inc(&input, pos)?),
             '+' => lex_a_token!(lex_add(&input, pos)?),
+            '-' if is_match(&input, pos, "--") => lex_a_token!(lex_dec(&input, pos)?),
+            '-' if is_match(&input, pos, "->") => lex_a_token!(lex_arrow(&input, pos)?),
             '-' => lex_a_token!(lex_sub(&input, pos)?),
             '*' => lex_a_token!(lex_mul(&input, pos)?),
             '/' => lex_a_token!(lex_quo(&input, pos)?),
+            '%' => lex_a_token!(lex_rem(&input, pos)?),
+            '&' if is_match(&input, pos, "&&") => lex_a_token!(lex_land(&input, pos)?),
+            '&' => lex_a_token!(lex_amp(&input, pos)?),
+            '|' if is_match(&input, pos, "||") => lex_a_token!(lex_lor(&input, pos)?),
+            '|' => lex_a_token!(lex_or(&input, pos)?),
+            '^' => lex_a_token!(lex_xor(&input, pos)?),
+            '~' => lex_a_token!(lex_tilde(&input, pos)?),
+            '!' => lex_a_token!(lex_not(&input, pos)?),
+            '?' => lex_a_token!(lex_question(&input, pos)?),
+            ':' => lex_a_token!(lex_colon(&input, pos)?),
             '(' => lex_a_token!(lex_lparen(&input, pos)?),
             ')' => lex_a_token!(lex_rparen(&input, pos)?),
+            '{' => lex_a_token!(lex_lbrace(&input, pos)?),
+            '}' => lex_a_token!(lex_rbrace(&input, pos)?),
+            '[' => lex_a_token!(lex_lbracket(&input, pos)?),
+            ']' => lex_a_token!(lex_rbracket(&input, pos)?),
+            ',' => lex_a_token!(lex_comma(&input, pos)?),
+            '.' => lex_a_token!(lex_dot(&input, pos)?),
             '=' => lex_a_token!(lex_assign(&input, pos)?),
+            '<' if is_match(&input, pos, "<<") => lex_a_token!(lex_shl(&input, pos)?),
             '<' => lex_a_token!(lex_lss(&input, pos)?),
+            '>' if is_match(&input, pos, ">>") => lex_a_token!(lex_shr(&input, pos)?),
             '>' => lex_a_token!(lex_grt(&input, pos)?),
             ';' => lex_a_token!(lex_eof(&input, pos)?),
-            _ => unimplemented!(),
+            _ => return Err(LexError::invalid_char(c, Loc(pos, pos + 1))),
         }
     }
 
@@ -260,6 +756,7 @@ pub fn lex(input: &str) -> Result<Vec<Token>, LexError> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use proptest::prelude::*;
 
     #[test]
     fn test_1() -> Result<(), LexError> {
@@ -324,7 +821,7 @@ mod tests {
         assert_eq!(
             tokens,
             vec!(
-                Token::return_(Loc(0, 6)),
+                Token::new(TokenKind::Return, Loc(0, 6)),
                 Token::int(1, Loc(7, 8)),
                 Token::eof(Loc(8, 9)),
             )
@@ -345,4 +842,504 @@ mod tests {
         );
         Ok(())
     }
+    #[test]
+    fn test_6() -> Result<(), LexError> {
+        let tokens = lex("if (a) 1; else 2;")?;
+        assert_eq!(
+            tokens,
+            vec!(
+                Token::new(TokenKind::If, Loc(0, 2)),
+                Token::lparen(Loc(3, 4)),
+                Token::ident("a", Loc(4, 5)),
+                Token::rparen(Loc(5, 6)),
+                Token::int(1, Loc(7, 8)),
+                Token::eof(Loc(8, 9)),
+                Token::new(TokenKind::Else, Loc(10, 14)),
+                Token::int(2, Loc(15, 16)),
+                Token::eof(Loc(16, 17)),
+            )
+        );
+        Ok(())
+    }
+    #[test]
+    fn test_7() -> Result<(), LexError> {
+        let tokens = lex("while (1) break; for (;;) continue;")?;
+        assert_eq!(
+            tokens,
+            vec!(
+                Token::new(TokenKind::While, Loc(0, 5)),
+                Token::lparen(Loc(6, 7)),
+                Token::int(1, Loc(7, 8)),
+                Token::rparen(Loc(8, 9)),
+                Token::new(TokenKind::Break, Loc(10, 15)),
+                Token::eof(Loc(15, 16)),
+                Token::new(TokenKind::For, Loc(17, 20)),
+                Token::lparen(Loc(21, 22)),
+                Token::eof(Loc(22, 23)),
+                Token::eof(Loc(23, 24)),
+                Token::rparen(Loc(24, 25)),
+                Token::new(TokenKind::Continue, Loc(26, 34)),
+                Token::eof(Loc(34, 35)),
+            )
+        );
+        Ok(())
+    }
+    #[test]
+    fn test_8() -> Result<(), LexError> {
+        let tokens = lex("{a;}")?;
+        assert_eq!(
+            tokens,
+            vec!(
+                Token::lbrace(Loc(0, 1)),
+                Token::ident("a", Loc(1, 2)),
+                Token::eof(Loc(2, 3)),
+                Token::rbrace(Loc(3, 4)),
+            )
+        );
+        Ok(())
+    }
+    #[test]
+    fn test_9() -> Result<(), LexError> {
+        let tokens = lex("f(a,b)")?;
+        assert_eq!(
+            tokens,
+            vec!(
+                Token::ident("f", Loc(0, 1)),
+                Token::lparen(Loc(1, 2)),
+                Token::ident("a", Loc(2, 3)),
+                Token::comma(Loc(3, 4)),
+                Token::ident("b", Loc(4, 5)),
+                Token::rparen(Loc(5, 6)),
+            )
+        );
+        Ok(())
+    }
+    #[test]
+    fn test_10() -> Result<(), LexError> {
+        let tokens = lex("int x;")?;
+        assert_eq!(
+            tokens,
+            vec!(
+                Token::new(TokenKind::IntType, Loc(0, 3)),
+                Token::ident("x", Loc(4, 5)),
+                Token::eof(Loc(5, 6)),
+            )
+        );
+        Ok(())
+    }
+    #[test]
+    fn test_11() -> Result<(), LexError> {
+        let tokens = lex("*&a")?;
+        assert_eq!(
+            tokens,
+            vec!(
+                Token::mul(Loc(0, 1)),
+                Token::amp(Loc(1, 2)),
+                Token::ident("a", Loc(2, 3)),
+            )
+        );
+        Ok(())
+    }
+    #[test]
+    fn test_12() -> Result<(), LexError> {
+        let tokens = lex("sizeof a[0]")?;
+        assert_eq!(
+            tokens,
+            vec!(
+                Token::new(TokenKind::Sizeof, Loc(0, 6)),
+                Token::ident("a", Loc(7, 8)),
+                Token::lbracket(Loc(8, 9)),
+                Token::int(0, Loc(9, 10)),
+                Token::rbracket(Loc(10, 11)),
+            )
+        );
+        Ok(())
+    }
+    #[test]
+    fn test_13() -> Result<(), LexError> {
+        let tokens = lex(r#"char *s = "a\n\"\x41\101\0b";"#)?;
+        assert_eq!(
+            tokens,
+            vec!(
+                Token::new(TokenKind::CharType, Loc(0, 4)),
+                Token::mul(Loc(5, 6)),
+                Token::ident("s", Loc(6, 7)),
+                Token::assign(Loc(8, 9)),
+                Token::str(b"a\n\"AA\0b", Loc(10, 28)),
+                Token::eof(Loc(28, 29)),
+            )
+        );
+        Ok(())
+    }
+    #[test]
+    fn test_14() -> Result<(), LexError> {
+        let tokens = lex("struct s { int a; } x; union u *p; x.a = p->b - _Alignof(x);")?;
+        let kinds: Vec<_> = tokens.into_iter().map(|t| t.value).collect();
+        assert_eq!(
+            kinds,
+            vec!(
+                TokenKind::Struct,
+                TokenKind::Ident("s".to_string()),
+                TokenKind::LBrace,
+                TokenKind::IntType,
+                TokenKind::Ident("a".to_string()),
+                TokenKind::Eof,
+                TokenKind::RBrace,
+                TokenKind::Ident("x".to_string()),
+                TokenKind::Eof,
+                TokenKind::Union,
+                TokenKind::Ident("u".to_string()),
+                TokenKind::Mul,
+                TokenKind::Ident("p".to_string()),
+                TokenKind::Eof,
+                TokenKind::Ident("x".to_string()),
+                TokenKind::Dot,
+                TokenKind::Ident("a".to_string()),
+                TokenKind::ASSIGN,
+                TokenKind::Ident("p".to_string()),
+                TokenKind::Arrow,
+                TokenKind::Ident("b".to_string()),
+                TokenKind::Sub,
+                TokenKind::Alignof,
+                TokenKind::LParen,
+                TokenKind::Ident("x".to_string()),
+                TokenKind::RParen,
+                TokenKind::Eof,
+            )
+        );
+        Ok(())
+    }
+    #[test]
+    fn test_15() -> Result<(), LexError> {
+        let tokens = lex("a%b&c|d^~e<<f>>g&&!h||i?j:k!=l")?;
+        let kinds: Vec<_> = tokens
+            .into_iter()
+            .map(|t| t.value)
+            .filter(|k| !matches!(k, TokenKind::Ident(_)))
+            .collect();
+        assert_eq!(
+            kinds,
+            vec!(
+                TokenKind::Rem,
+                TokenKind::Amp,
+                TokenKind::Or,
+                TokenKind::Xor,
+                TokenKind::Tilde,
+                TokenKind::Shl,
+                TokenKind::Shr,
+                TokenKind::LAnd,
+                TokenKind::Not,
+                TokenKind::LOr,
+                TokenKind::Question,
+                TokenKind::Colon,
+                TokenKind::NEQ,
+            )
+        );
+        Ok(())
+    }
+    #[test]
+    fn test_16() -> Result<(), LexError> {
+        let tokens = lex("a+=b-=c*=d/=e%=f&=g|=h^=i<<=j>>=k++--l<<m<=n;")?;
+        let kinds: Vec<_> = tokens
+            .into_iter()
+            .map(|t| t.value)
+            .filter(|k| !matches!(k, TokenKind::Ident(_)))
+            .collect();
+        assert_eq!(
+            kinds,
+            vec!(
+                TokenKind::AddAssign,
+                TokenKind::SubAssign,
+                TokenKind::MulAssign,
+                TokenKind::QuoAssign,
+                TokenKind::RemAssign,
+                TokenKind::AndAssign,
+                TokenKind::OrAssign,
+                TokenKind::XorAssign,
+                TokenKind::ShlAssign,
+                TokenKind::ShrAssign,
+                TokenKind::Inc,
+                TokenKind::Dec,
+                TokenKind::Shl,
+                TokenKind::LEQ,
+                TokenKind::Eof,
+            )
+        );
+        Ok(())
+    }
+    #[test]
+    fn test_17() -> Result<(), LexError> {
+        let tokens = lex("switch(x){case 1:default:break;}")?;
+        let kinds: Vec<_> = tokens.into_iter().map(|t| t.value).collect();
+        assert_eq!(
+            kinds,
+            vec!(
+                TokenKind::Switch,
+                TokenKind::LParen,
+                TokenKind::Ident("x".to_string()),
+                TokenKind::RParen,
+                TokenKind::LBrace,
+                TokenKind::Case,
+                TokenKind::Int(1, Type::Int),
+                TokenKind::Colon,
+                TokenKind::Default,
+                TokenKind::Colon,
+                TokenKind::Break,
+                TokenKind::Eof,
+                TokenKind::RBrace,
+            )
+        );
+        Ok(())
+    }
+    #[test]
+    fn test_18() -> Result<(), LexError> {
+        let tokens = lex("do double while;")?;
+        let kinds: Vec<_> = tokens.into_iter().map(|t| t.value).collect();
+        assert_eq!(
+            kinds,
+            vec!(
+                TokenKind::Do,
+                TokenKind::DoubleType,
+                TokenKind::While,
+                TokenKind::Eof
+            )
+        );
+        Ok(())
+    }
+    #[test]
+    fn test_19() -> Result<(), LexError> {
+        let tokens = lex("typedef enum const volatile restrict static extern inline;")?;
+        let kinds: Vec<_> = tokens.into_iter().map(|t| t.value).collect();
+        assert_eq!(
+            kinds,
+            vec!(
+                TokenKind::Typedef,
+                TokenKind::Enum,
+                TokenKind::Const,
+                TokenKind::Volatile,
+                TokenKind::Restrict,
+                TokenKind::Static,
+                TokenKind::Extern,
+                TokenKind::Inline,
+                TokenKind::Eof
+            )
+        );
+        Ok(())
+    }
+    #[test]
+    fn test_keywords() -> Result<(), LexError> {
+        for (name, kind) in KEYWORDS {
+            let end = name.chars().count();
+            assert_eq!(lex(name)?, vec![Token::new(kind.clone(), Loc(0, end))]);
+        }
+        Ok(())
+    }
+    #[test]
+    fn test_keyword_prefixes() -> Result<(), LexError> {
+        for input in &[
+            "returned",
+            "do_not",
+            "integer",
+            "iffy",
+            "constant",
+            "intp",
+            "doubles",
+            "forx",
+            "sizeof1",
+            "_Alignofx",
+            "_Bool_",
+            "Return",
+            "dou",
+        ] {
+            let end = input.chars().count();
+            assert_eq!(lex(input)?, vec![Token::ident(input, Loc(0, end))]);
+        }
+        let tokens = lex("return returned;")?;
+        assert_eq!(
+            tokens,
+            vec!(
+                Token::new(TokenKind::Return, Loc(0, 6)),
+                Token::ident("returned", Loc(7, 15)),
+                Token::eof(Loc(15, 16)),
+            )
+        );
+        Ok(())
+    }
+    #[test]
+    fn test_int_literals() -> Result<(), LexError> {
+        let cases = vec![
+            ("0", 0, Type::Int),
+            ("017", 15, Type::Int),
+            ("0x1F", 31, Type::Int),
+            ("0B101", 5, Type::Int),
+            ("2147483647", 2147483647, Type::Int),
+            ("2147483648", 2147483648, Type::Long),
+            ("0x80000000", 0x8000_0000, Type::UInt),
+            ("0x100000000", 0x1_0000_0000, Type::Long),
+            ("0xffffffffffffffff", -1, Type::ULong),
+            ("1u", 1, Type::UInt),
+            ("4294967296U", 4294967296, Type::ULong),
+            ("1l", 1, Type::Long),
+            ("1LL", 1, Type::Long),
+            ("1ul", 1, Type::ULong),
+            ("1LLU", 1, Type::ULong),
+        ];
+        for (input, n, ty) in cases {
+            let tokens = lex(input)?;
+            assert_eq!(tokens[0].value, TokenKind::Int(n, ty), "{}", input);
+            assert_eq!(tokens[0].loc, Loc(0, input.len()));
+        }
+        Ok(())
+    }
+    #[test]
+    fn test_float_literals() -> Result<(), LexError> {
+        let cases = vec![
+            ("1.5", 1.5, Type::Double),
+            ("1.", 1.0, Type::Double),
+            (".25", 0.25, Type::Double),
+            ("1e3", 1000.0, Type::Double),
+            ("2.5E-1", 0.25, Type::Double),
+            ("1.5e+2f", 150.0, Type::Float),
+            ("0.5F", 0.5, Type::Float),
+            ("3.0l", 3.0, Type::Double),
+        ];
+        for (input, f, ty) in cases {
+            let tokens = lex(input)?;
+            assert_eq!(tokens[0].value, TokenKind::Float(f, ty), "{}", input);
+            assert_eq!(tokens[0].loc, Loc(0, input.len()));
+        }
+        let tokens = lex("a.b-1.e-1")?;
+        assert_eq!(tokens[1].value, TokenKind::Dot);
+        assert_eq!(tokens[3].value, TokenKind::Sub);
+        assert_eq!(tokens[4].value, TokenKind::Float(0.1, Type::Double));
+        Ok(())
+    }
+    #[test]
+    fn test_invalid_int_literals() {
+        for input in &[
+            "09",
+            "0x",
+            "0b2",
+            "1a",
+            "1lul",
+            "1.5x",
+            "1e",
+            "1.2.3",
+            "9223372036854775808",
+            "18446744073709551616",
+        ] {
+            match lex(input) {
+                Err(LexError {
+                    value: LexErrorKind::InvalidNumber,
+                    loc,
+                }) => assert_eq!(loc, Loc(0, input.len())),
+                r => panic!("{}: {:?}", input, r),
+            }
+        }
+    }
+    #[test]
+    fn test_invalid_chars() {
+        for &(input, pos) in &[("$", 0), ("a @ b", 2), ("1 + `", 4), ("é", 0), ("x \\", 2)] {
+            match lex(input) {
+                Err(LexError {
+                    value: LexErrorKind::InvalidChar(_),
+                    loc,
+                }) => assert_eq!(loc, Loc(pos, pos + 1), "{}", input),
+                r => panic!("{}: {:?}", input, r),
+            }
+        }
+    }
+    #[test]
+    fn test_operator_at_end() -> Result<(), LexError> {
+        for (input, kind) in &[
+            ("=", TokenKind::ASSIGN),
+            ("<", TokenKind::LSS),
+            (">", TokenKind::GRT),
+            ("!", TokenKind::Not),
+        ] {
+            assert_eq!(lex(input)?, vec![Token::new(kind.clone(), Loc(0, 1))]);
+        }
+        for (input, kind) in &[
+            ("++", TokenKind::Inc),
+            ("--", TokenKind::Dec),
+            ("->", TokenKind::Arrow),
+            ("&&", TokenKind::LAnd),
+            ("||", TokenKind::LOr),
+            ("<<", TokenKind::Shl),
+            (">>", TokenKind::Shr),
+        ] {
+            assert_eq!(lex(input)?, vec![Token::new(kind.clone(), Loc(0, 2))]);
+        }
+        assert_eq!(lex("a<<=")?[1], Token::new(TokenKind::ShlAssign, Loc(1, 4)));
+        Ok(())
+    }
+    #[test]
+    fn test_unclosed_str() {
+        match lex("\"abc") {
+            Err(LexError {
+                value: LexErrorKind::UnclosedStr,
+                loc,
+            }) => assert_eq!(loc, Loc(0, 4)),
+            _ => panic!(),
+        }
+    }
+    #[test]
+    fn test_whitespace_and_comments() -> Result<(), LexError> {
+        let tokens = lex("a\t/* x\n*/b // c\r\n\x0cc/**/;//")?;
+        assert_eq!(
+            tokens,
+            vec!(
+                Token::ident("a", Loc(0, 1)),
+                Token::ident("b", Loc(9, 10)),
+                Token::ident("c", Loc(18, 19)),
+                Token::eof(Loc(23, 24)),
+            )
+        );
+        // A comment ends at the first `*/`, and `/*/` does not close one.
+        let tokens = lex("/*/ */ a /* /* */ */")?;
+        assert_eq!(tokens[0], Token::ident("a", Loc(7, 8)));
+        assert_eq!(tokens[1].value, TokenKind::Mul);
+        assert_eq!(tokens[2].value, TokenKind::Quo);
+        // Division, not a comment.
+        let tokens = lex("a / b /= c")?;
+        assert_eq!(tokens[1], Token::new(TokenKind::Quo, Loc(2, 3)));
+        assert_eq!(tokens[3], Token::new(TokenKind::QuoAssign, Loc(6, 8)));
+        Ok(())
+    }
+    #[test]
+    fn test_unclosed_comment() {
+        for input in &["a /* b", "a /*/", "a /* b *"] {
+            match lex(input) {
+                Err(LexError {
+                    value: LexErrorKind::UnclosedComment,
+                    loc,
+                }) => assert_eq!(loc, Loc(2, 4)),
+                r => panic!("{}: {:?}", input, r),
+            }
+        }
+    }
+
+    // Pieces of which random inputs are made, so that they run into the
+    // ends of tokens more often than arbitrary strings do.
+    const PIECES: &[&str] = &[
+        "a", "_", "1", "0x", "0b", "9", ".", "e", "f", "u", "l", "+", "-", "*", "/", "%", "&", "|",
+        "^", "~", "!", "=", "<", ">", "?", ":", ";", ",", "(", ")", "{", "}", "[", "]", "\"", "'",
+        "\\", " ", "\n", "\t", "\r", "//", "/*", "*/", "do", "int", "return", "é", "$",
+    ];
+
+    proptest! {
+        // Any input is either lexed or rejected with an error inside it.
+        #[test]
+        fn test_lex_is_total(
+            input in prop_oneof![
+                "\\PC*",
+                prop::collection::vec(prop::sample::select(PIECES), 0..20).prop_map(|v| v.concat()),
+            ]
+        ) {
+            if let Err(err) = lex(&input) {
+                prop_assert!(err.loc.0 <= err.loc.1);
+                prop_assert!(err.loc.1 <= input.chars().count());
+            }
+        }
+    }
 }
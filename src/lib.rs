@@ -1,3 +1,6 @@
+pub mod diagnostics;
 pub mod generator;
 pub mod lexer;
 pub mod parser;
+pub mod sema;
+pub mod types;
@@ -1,30 +1,61 @@
 extern crate rchan;
 
+use rchan::diagnostics::{Diagnostic, Source};
 use rchan::generator;
 use rchan::lexer;
 use rchan::parser;
+use rchan::sema;
 use std::env;
+use std::process;
 
-fn parse_arguments() -> Result<String, std::io::Error> {
-    let args: Vec<String> = env::args().collect();
-    if args.len() != 2 {
-        let e = std::io::Error::new(
-            std::io::ErrorKind::InvalidInput,
-            "Arguments should have 1 parameters".to_string(),
-        );
-        return Err(e);
+// Accepts `[--error-limit=N] program`, returning the program and the
+// number of parse errors to report at most.
+fn parse_arguments() -> Result<(String, usize), std::io::Error> {
+    let invalid =
+        |message: &str| std::io::Error::new(std::io::ErrorKind::InvalidInput, message.to_string());
+    let mut args: Vec<String> = env::args().skip(1).collect();
+    let mut max_errors = parser::DEFAULT_ERROR_LIMIT;
+    if let Some(limit) = args.first().and_then(|a| a.strip_prefix("--error-limit=")) {
+        max_errors = limit
+            .parse()
+            .map_err(|_| invalid("--error-limit should be a number"))?;
+        args.remove(0);
+    }
+    if args.len() != 1 {
+        return Err(invalid("Arguments should have 1 parameters"));
     }
 
-    Ok(args[1].clone())
+    Ok((args.remove(0), max_errors))
 }
 
-fn main() -> Result<(), std::io::Error> {
-    let input = parse_arguments()?;
-    let tokens = lexer::lex(&input).unwrap();
-    // println!("{:?}", tokens);
-    let code = parser::parse(tokens).unwrap();
-    // println!("{:?}", code);
-    generator::code_gen(code);
-
+// Compiles the program to assembly on stdout, or reports its errors. Parse
+// errors are all reported, up to the limit; only the first error of the
+// other phases is.
+fn compile(input: &str, source: &Source, max_errors: usize) -> Result<(), Vec<Diagnostic>> {
+    let tokens = lexer::lex(input).map_err(|e| vec![source.lex_error(&e)])?;
+    let mut program = parser::parse_with_limit(tokens, max_errors).map_err(|errors| {
+        errors
+            .iter()
+            .map(|e| source.parse_error(e))
+            .collect::<Vec<_>>()
+    })?;
+    sema::analyze(&mut program).map_err(|e| vec![source.sema_error(&e)])?;
+    generator::code_gen(program);
     Ok(())
 }
+
+fn main() {
+    let (input, max_errors) = match parse_arguments() {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            process::exit(1);
+        }
+    };
+    let source = Source::new("<input>", &input);
+    if let Err(diags) = compile(&input, &source, max_errors) {
+        let rendered: Vec<String> = diags.iter().map(|d| source.render(d)).collect();
+        eprint!("{}", rendered.join("\n"));
+        process::exit(1);
+    }
+}
@@ -1,51 +1,278 @@
 use super::lexer::*;
-use std::cell::RefCell;
+use super::sema;
+use super::types::*;
 use std::iter::Peekable;
+use std::rc::Rc;
 
 #[derive(Debug, PartialEq)]
 pub enum NodeKind {
-    Int(i32),
-    LocalVariable(i32),
+    Int(i64),
+    Float(f64),
+    // A string literal, without the terminating NUL.
+    Str(Vec<u8>),
+    LocalVariable(LocalVariable),
+    GlobalVariable(GlobalVariable),
     Assign {
         lhs: Box<Node>,
         rhs: Box<Node>,
     },
+    // `lhs op= rhs`, or `lhs++` and `lhs--` with `old`, whose value is that
+    // of `lhs` before the assignment. `addr` and `old` are temporaries that
+    // `sema::analyze` uses to lower the node into plain assignments.
+    CompoundAssign {
+        op: BinOp,
+        lhs: Box<Node>,
+        rhs: Box<Node>,
+        addr: LocalVariable,
+        old: Option<LocalVariable>,
+    },
     Return(Box<Node>),
+    If {
+        cond: Box<Node>,
+        then: Box<Node>,
+        els: Option<Box<Node>>,
+    },
+    Block(Vec<Node>),
+    While {
+        cond: Box<Node>,
+        body: Box<Node>,
+    },
+    // `do body while (cond);`
+    DoWhile {
+        body: Box<Node>,
+        cond: Box<Node>,
+    },
+    For {
+        init: Option<Box<Node>>,
+        cond: Option<Box<Node>>,
+        step: Option<Box<Node>>,
+        body: Box<Node>,
+    },
+    Break,
+    Continue,
+    // `switch (cond) body`, where `body` holds the `Case` and `Default`
+    // statements that `cases` and `has_default` describe.
+    Switch {
+        cond: Box<Node>,
+        body: Box<Node>,
+        cases: Vec<SwitchCase>,
+        has_default: bool,
+    },
+    // `case expr: stmt`, labelled by `index` into the `cases` of the
+    // enclosing switch.
+    Case {
+        index: usize,
+        stmt: Box<Node>,
+    },
+    // `default: stmt`
+    Default(Box<Node>),
+    // `name: stmt`
+    Label {
+        name: String,
+        stmt: Box<Node>,
+    },
+    // `goto name;`
+    Goto(String),
+    FunCall {
+        name: String,
+        args: Vec<Node>,
+    },
+    UniOp {
+        op: UniOp,
+        expr: Box<Node>,
+    },
+    // `expr.name`; `offset` is resolved by `sema::analyze`.
+    Member {
+        expr: Box<Node>,
+        name: String,
+        offset: i32,
+    },
+    // Replaced with the size of the operand's type by `sema::analyze`.
+    Sizeof(Box<Node>),
+    // Converts the operand to the type of this node. Inserted by `sema::analyze`.
+    Cast(Box<Node>),
     BinOp {
         op: BinOp,
         lhs: Box<Node>,
         rhs: Box<Node>,
     },
+    // `cond ? then : els`
+    Conditional {
+        cond: Box<Node>,
+        then: Box<Node>,
+        els: Box<Node>,
+    },
+    // `lhs, rhs`
+    Comma {
+        lhs: Box<Node>,
+        rhs: Box<Node>,
+    },
 }
 
 #[derive(Debug, PartialEq)]
 pub enum UniOpKind {
-    Minus, // -
+    Minus,  // -
+    Addr,   // &
+    Deref,  // *
+    Not,    // !
+    BitNot, // ~
 }
 
 #[derive(Debug, PartialEq)]
 pub enum BinOpKind {
-    Add, // +
-    Sub, // -
-    Mul, // *
-    Quo, // /
-    EQ,  // ==
-    NEQ, // !=
-    LSS, // <
-    LEQ, // <=
+    Add,    // +
+    Sub,    // -
+    Mul,    // *
+    Quo,    // /
+    Rem,    // %
+    BitAnd, // &
+    BitOr,  // |
+    BitXor, // ^
+    Shl,    // <<
+    Shr,    // >>
+    EQ,     // ==
+    NEQ,    // !=
+    LSS,    // <
+    LEQ,    // <=
+    LogAnd, // &&
+    LogOr,  // ||
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Node {
+    pub value: NodeKind,
+    pub loc: Loc,
+    // Type of the expression, resolved by `sema::analyze`. A numeric
+    // literal has its type from the start.
+    pub ty: Option<Type>,
 }
 
-pub type Node = Annot<NodeKind>;
 impl Node {
-    fn new_int(n: i32, loc: Loc) -> Self {
-        Self::new(NodeKind::Int(n), loc)
+    pub fn new(value: NodeKind, loc: Loc) -> Self {
+        Node {
+            value,
+            loc,
+            ty: None,
+        }
+    }
+    fn new_int(n: i64, ty: Type, loc: Loc) -> Self {
+        Node {
+            value: NodeKind::Int(n),
+            loc,
+            ty: Some(ty),
+        }
+    }
+    fn new_float(f: f64, ty: Type, loc: Loc) -> Self {
+        Node {
+            value: NodeKind::Float(f),
+            loc,
+            ty: Some(ty),
+        }
+    }
+    fn new_str(bytes: Vec<u8>, loc: Loc) -> Self {
+        Self::new(NodeKind::Str(bytes), loc)
     }
-    fn new_lvar(offset: i32, loc: Loc) -> Self {
-        Self::new(NodeKind::LocalVariable(offset), loc)
+    fn new_lvar(var: LocalVariable, loc: Loc) -> Self {
+        Self::new(NodeKind::LocalVariable(var), loc)
+    }
+    fn new_gvar(var: GlobalVariable, loc: Loc) -> Self {
+        Self::new(NodeKind::GlobalVariable(var), loc)
     }
     fn new_return(node: Node, loc: Loc) -> Self {
         Self::new(NodeKind::Return(Box::new(node)), loc)
     }
+    fn new_if(cond: Node, then: Node, els: Option<Node>, loc: Loc) -> Self {
+        Self::new(
+            NodeKind::If {
+                cond: Box::new(cond),
+                then: Box::new(then),
+                els: els.map(Box::new),
+            },
+            loc,
+        )
+    }
+    fn new_block(stmts: Vec<Node>, loc: Loc) -> Self {
+        Self::new(NodeKind::Block(stmts), loc)
+    }
+    fn new_while(cond: Node, body: Node, loc: Loc) -> Self {
+        Self::new(
+            NodeKind::While {
+                cond: Box::new(cond),
+                body: Box::new(body),
+            },
+            loc,
+        )
+    }
+    fn new_do_while(body: Node, cond: Node, loc: Loc) -> Self {
+        Self::new(
+            NodeKind::DoWhile {
+                body: Box::new(body),
+                cond: Box::new(cond),
+            },
+            loc,
+        )
+    }
+    fn new_for(
+        init: Option<Node>,
+        cond: Option<Node>,
+        step: Option<Node>,
+        body: Node,
+        loc: Loc,
+    ) -> Self {
+        Self::new(
+            NodeKind::For {
+                init: init.map(Box::new),
+                cond: cond.map(Box::new),
+                step: step.map(Box::new),
+                body: Box::new(body),
+            },
+            loc,
+        )
+    }
+    fn new_break(loc: Loc) -> Self {
+        Self::new(NodeKind::Break, loc)
+    }
+    fn new_continue(loc: Loc) -> Self {
+        Self::new(NodeKind::Continue, loc)
+    }
+    fn new_switch(cond: Node, body: Node, labels: SwitchLabels, loc: Loc) -> Self {
+        Self::new(
+            NodeKind::Switch {
+                cond: Box::new(cond),
+                body: Box::new(body),
+                cases: labels.cases,
+                has_default: labels.has_default,
+            },
+            loc,
+        )
+    }
+    fn new_case(index: usize, stmt: Node, loc: Loc) -> Self {
+        Self::new(
+            NodeKind::Case {
+                index,
+                stmt: Box::new(stmt),
+            },
+            loc,
+        )
+    }
+    fn new_default(stmt: Node, loc: Loc) -> Self {
+        Self::new(NodeKind::Default(Box::new(stmt)), loc)
+    }
+    fn new_label(name: String, stmt: Node, loc: Loc) -> Self {
+        Self::new(
+            NodeKind::Label {
+                name,
+                stmt: Box::new(stmt),
+            },
+            loc,
+        )
+    }
+    fn new_goto(name: String, loc: Loc) -> Self {
+        Self::new(NodeKind::Goto(name), loc)
+    }
+    fn new_funcall(name: String, args: Vec<Node>, loc: Loc) -> Self {
+        Self::new(NodeKind::FunCall { name, args }, loc)
+    }
     fn new_assign(lhs: Node, rhs: Node, loc: Loc) -> Self {
         Self::new(
             NodeKind::Assign {
@@ -55,6 +282,56 @@ impl Node {
             loc,
         )
     }
+    // `tok` is the operator, at which a lack of room for the temporaries in
+    // the stack frame is reported.
+    fn new_compound_assign(
+        op: BinOp,
+        lhs: Node,
+        rhs: Node,
+        ctx: &mut Context,
+        postfix: bool,
+        tok: &Token,
+    ) -> Result<Self, ParseError> {
+        let loc = op.loc;
+        let addr = ctx.new_temp(tok)?;
+        let old = if postfix {
+            Some(ctx.new_temp(tok)?)
+        } else {
+            None
+        };
+        Ok(Self::new(
+            NodeKind::CompoundAssign {
+                op,
+                lhs: Box::new(lhs),
+                rhs: Box::new(rhs),
+                addr,
+                old,
+            },
+            loc,
+        ))
+    }
+    fn new_uniop(op: UniOp, expr: Node, loc: Loc) -> Self {
+        Self::new(
+            NodeKind::UniOp {
+                op,
+                expr: Box::new(expr),
+            },
+            loc,
+        )
+    }
+    fn new_member(expr: Node, name: String, loc: Loc) -> Self {
+        Self::new(
+            NodeKind::Member {
+                expr: Box::new(expr),
+                name,
+                offset: 0,
+            },
+            loc,
+        )
+    }
+    fn new_sizeof(node: Node, loc: Loc) -> Self {
+        Self::new(NodeKind::Sizeof(Box::new(node)), loc)
+    }
     fn new_binop(op: BinOp, lhs: Node, rhs: Node, loc: Loc) -> Self {
         Self::new(
             NodeKind::BinOp {
@@ -65,12 +342,86 @@ impl Node {
             loc,
         )
     }
+    fn new_conditional(cond: Node, then: Node, els: Node, loc: Loc) -> Self {
+        Self::new(
+            NodeKind::Conditional {
+                cond: Box::new(cond),
+                then: Box::new(then),
+                els: Box::new(els),
+            },
+            loc,
+        )
+    }
+    fn new_comma(lhs: Node, rhs: Node, loc: Loc) -> Self {
+        Self::new(
+            NodeKind::Comma {
+                lhs: Box::new(lhs),
+                rhs: Box::new(rhs),
+            },
+            loc,
+        )
+    }
+}
+
+#[derive(Debug, PartialEq, Default)]
+pub struct Program {
+    pub functions: Vec<Function>,
+    pub prototypes: Vec<Prototype>,
+    pub globals: Vec<Global>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Function {
+    pub name: String,
+    pub ret_ty: Type,
+    pub params: Vec<LocalVariable>,
+    pub body: Node,
+    pub locals: LocalVariables,
+    // Declared `static`, so that the name is not visible to the linker.
+    pub is_static: bool,
+    pub loc: Loc,
+}
+
+// A function declaration without a body, such as `double sqrt(double);`.
+#[derive(Debug, PartialEq)]
+pub struct Prototype {
+    pub name: String,
+    pub ret_ty: Type,
+    pub params: Vec<Type>,
+    pub loc: Loc,
 }
 
-type UniOp = Annot<UniOpKind>;
-impl UniOp {}
+// The value of a `case` label. `value` is `expr` converted to the type of
+// the switch condition, computed by `sema::analyze`.
+#[derive(Debug, PartialEq)]
+pub struct SwitchCase {
+    pub expr: Node,
+    pub value: i64,
+}
+
+// Number of arguments that are passed in registers.
+pub const MAX_ARGS: usize = 6;
+
+pub type UniOp = Annot<UniOpKind>;
+impl UniOp {
+    fn new_addr(loc: Loc) -> Self {
+        Self::new(UniOpKind::Addr, loc)
+    }
+    fn new_deref(loc: Loc) -> Self {
+        Self::new(UniOpKind::Deref, loc)
+    }
+    fn new_minus(loc: Loc) -> Self {
+        Self::new(UniOpKind::Minus, loc)
+    }
+    fn new_not(loc: Loc) -> Self {
+        Self::new(UniOpKind::Not, loc)
+    }
+    fn new_bitnot(loc: Loc) -> Self {
+        Self::new(UniOpKind::BitNot, loc)
+    }
+}
 
-type BinOp = Annot<BinOpKind>;
+pub type BinOp = Annot<BinOpKind>;
 impl BinOp {
     fn new_add(loc: Loc) -> Self {
         Self::new(BinOpKind::Add, loc)
@@ -84,6 +435,30 @@ impl BinOp {
     fn new_quo(loc: Loc) -> Self {
         Self::new(BinOpKind::Quo, loc)
     }
+    fn new_rem(loc: Loc) -> Self {
+        Self::new(BinOpKind::Rem, loc)
+    }
+    fn new_bitand(loc: Loc) -> Self {
+        Self::new(BinOpKind::BitAnd, loc)
+    }
+    fn new_bitor(loc: Loc) -> Self {
+        Self::new(BinOpKind::BitOr, loc)
+    }
+    fn new_bitxor(loc: Loc) -> Self {
+        Self::new(BinOpKind::BitXor, loc)
+    }
+    fn new_shl(loc: Loc) -> Self {
+        Self::new(BinOpKind::Shl, loc)
+    }
+    fn new_shr(loc: Loc) -> Self {
+        Self::new(BinOpKind::Shr, loc)
+    }
+    fn new_logand(loc: Loc) -> Self {
+        Self::new(BinOpKind::LogAnd, loc)
+    }
+    fn new_logor(loc: Loc) -> Self {
+        Self::new(BinOpKind::LogOr, loc)
+    }
     fn new_eq(loc: Loc) -> Self {
         Self::new(BinOpKind::EQ, loc)
     }
@@ -98,127 +473,1526 @@ impl BinOp {
     }
 }
 
-#[derive(Debug, PartialEq)]
-struct LocalVariable {
-    name: String,
+#[derive(Debug, PartialEq, Clone)]
+pub struct LocalVariable {
+    pub name: String,
+    pub ty: Type,
+    // Distance from rbp to the start of the variable.
+    pub offset: i32,
 }
 
 impl LocalVariable {
-    fn new(name: &str) -> Self {
+    fn new(name: &str, ty: Type) -> Self {
         LocalVariable {
             name: name.to_string(),
+            ty,
+            offset: 0,
         }
     }
 }
 
-#[derive(Debug)]
-struct LocalVariables(Vec<LocalVariable>);
+#[derive(Debug, PartialEq, Clone)]
+pub struct GlobalVariable {
+    pub name: String,
+    pub ty: Type,
+}
+
+// A variable with static storage, defined at file scope.
+#[derive(Debug, PartialEq)]
+pub struct Global {
+    pub var: GlobalVariable,
+    // Zero-initialized in `.bss` when absent.
+    pub init: Option<Initializer>,
+    // Declared `static`, so that the name is not visible to the linker.
+    pub is_static: bool,
+    pub loc: Loc,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Initializer {
+    Expr(Node),
+    // `{ a, b, ... }`, initializing the elements of an array.
+    List(Vec<Initializer>, Loc),
+    // Replaced with the bytes of the initial value by `sema::analyze`.
+    Data(StaticData),
+}
+
+// The initial contents of a global, with the addresses that the linker fills in.
+#[derive(Debug, PartialEq)]
+pub struct StaticData {
+    pub bytes: Vec<u8>,
+    pub relocs: Vec<Reloc>,
+}
+
+// An 8-byte address at `offset` in the data, pointing `addend` bytes past `target`.
+#[derive(Debug, PartialEq)]
+pub struct Reloc {
+    pub offset: usize,
+    pub target: RelocTarget,
+    pub addend: i64,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum RelocTarget {
+    Global(String),
+    Str(Vec<u8>),
+}
+
+#[derive(Debug, PartialEq)]
+pub struct LocalVariables {
+    // Every variable declared in the function, in declaration order.
+    vars: Vec<LocalVariable>,
+    // Indices into `vars` of the variables currently in scope, innermost last.
+    visible: Vec<usize>,
+    // Length of `visible` at the start of each open block.
+    scopes: Vec<usize>,
+}
 impl LocalVariables {
     fn new() -> Self {
-        LocalVariables(Vec::<LocalVariable>::new())
+        LocalVariables {
+            vars: Vec::<LocalVariable>::new(),
+            visible: Vec::<usize>::new(),
+            scopes: Vec::<usize>::new(),
+        }
+    }
+    fn push(&mut self, mut var: LocalVariable) -> &LocalVariable {
+        let bottom = self.vars.last().map_or(0, |v| v.offset);
+        var.offset = align_to(bottom + var.ty.size(), var.ty.align());
+        self.vars.push(var);
+        self.visible.push(self.vars.len() - 1);
+        self.vars.last().unwrap()
     }
-    fn push(&mut self, var: LocalVariable) -> i32 {
-        self.0.push(var);
-        (self.0.len() as i32) * 8
+    // Tells whether a variable of type `ty` still fits in the frame.
+    fn has_room_for(&self, ty: &Type) -> bool {
+        let bottom = self.vars.last().map_or(0, |v| v.offset);
+        bottom + ty.size() <= MAX_SIZE
     }
-    fn find_and_get_offset(&mut self, s: &str) -> Option<i32> {
-        self.0
+    fn find(&self, s: &str) -> Option<&LocalVariable> {
+        self.visible
             .iter()
-            .position(|x| x.name == s)
-            .map(|x| ((x as i32) + 1) * 8)
+            .rev()
+            .map(|&i| &self.vars[i])
+            .find(|v| v.name == s)
+    }
+    fn enter_scope(&mut self) {
+        self.scopes.push(self.visible.len());
+    }
+    fn leave_scope(&mut self) {
+        let len = self.scopes.pop().unwrap();
+        self.visible.truncate(len);
     }
+    pub fn iter(&self) -> std::slice::Iter<'_, LocalVariable> {
+        self.vars.iter()
+    }
+    // Size of the stack frame holding every variable, keeping rsp 16-byte aligned.
+    pub fn stack_size(&self) -> i32 {
+        align_to(self.vars.last().map_or(0, |v| v.offset), 16)
+    }
+}
+
+#[derive(Debug)]
+struct Context {
+    // Global variables defined so far.
+    globals: Vec<GlobalVariable>,
+    // Locals of the function being parsed; empty at file scope.
+    lvars: LocalVariables,
+    // Ordinary identifiers in scope, innermost last.
+    symbols: Vec<(String, Symbol)>,
+    // Length of `symbols` at the start of each open block.
+    symbol_scopes: Vec<usize>,
+    // Struct, union and enum tags in scope, innermost last.
+    tags: Vec<(String, Tag)>,
+    // Length of `tags` at the start of each open block.
+    tag_scopes: Vec<usize>,
+    // Static local variables of the functions parsed so far, which are
+    // emitted as globals.
+    static_locals: Vec<Global>,
+    // Number of loops enclosing the current statement.
+    loop_depth: usize,
+    // Labels of the switch statements enclosing the current statement,
+    // innermost last.
+    switches: Vec<SwitchLabels>,
+    // Labels defined so far in the function being parsed.
+    labels: Vec<String>,
+    // Targets of the `goto` statements of the function being parsed, which
+    // are checked against `labels` at its end.
+    gotos: Vec<(String, Loc)>,
+    // Errors recovered from so far.
+    errors: Vec<ParseError>,
+    // Number of errors after which parsing stops, or 0 for no limit.
+    max_errors: usize,
+}
+
+// What an ordinary identifier declares.
+#[derive(Debug)]
+enum Symbol {
+    // A local variable, found in `Context::lvars`.
+    Local,
+    // A global variable, or a static local under its assembly name.
+    Global(GlobalVariable),
+    Typedef(Type),
+    EnumConst(i64),
+}
+
+#[derive(Debug)]
+enum Tag {
+    Struct(Rc<StructType>),
+    Enum,
+}
+
+// The storage class of a declaration. `inline` is accepted but has no
+// effect.
+#[derive(Debug, Default)]
+struct DeclAttr {
+    is_typedef: bool,
+    is_static: bool,
+    is_extern: bool,
+}
+
+// The labels found so far in the body of a switch.
+#[derive(Debug, Default)]
+struct SwitchLabels {
+    cases: Vec<SwitchCase>,
+    has_default: bool,
 }
 
-thread_local!(
-    static LVARS: RefCell<LocalVariables> = { RefCell::new(LocalVariables::new()) };
-);
+impl Context {
+    fn new() -> Self {
+        Context {
+            globals: Vec::<GlobalVariable>::new(),
+            lvars: LocalVariables::new(),
+            symbols: Vec::<(String, Symbol)>::new(),
+            symbol_scopes: Vec::<usize>::new(),
+            tags: Vec::<(String, Tag)>::new(),
+            tag_scopes: Vec::<usize>::new(),
+            static_locals: Vec::<Global>::new(),
+            loop_depth: 0,
+            switches: Vec::<SwitchLabels>::new(),
+            labels: Vec::<String>::new(),
+            errors: Vec::<ParseError>::new(),
+            max_errors: DEFAULT_ERROR_LIMIT,
+            gotos: Vec::<(String, Loc)>::new(),
+        }
+    }
+    fn enter_scope(&mut self) {
+        self.lvars.enter_scope();
+        self.symbol_scopes.push(self.symbols.len());
+        self.tag_scopes.push(self.tags.len());
+    }
+    fn leave_scope(&mut self) {
+        self.lvars.leave_scope();
+        let len = self.symbol_scopes.pop().unwrap();
+        self.symbols.truncate(len);
+        let len = self.tag_scopes.pop().unwrap();
+        self.tags.truncate(len);
+    }
+    // Closes the scopes that an error left open, back to file scope.
+    fn leave_all_scopes(&mut self) {
+        while !self.symbol_scopes.is_empty() {
+            self.leave_scope();
+        }
+    }
+    // Records an error to report once parsing ends. Fails with
+    // `TooManyErrors` when the limit is reached, to stop parsing.
+    fn report(&mut self, err: ParseError) -> Result<(), ParseError> {
+        match err {
+            ParseError::TooManyErrors => return Err(err),
+            // Each enclosing block also runs into the end of the input.
+            ParseError::Eof if self.errors.iter().any(|e| matches!(e, ParseError::Eof)) => {
+                return Ok(())
+            }
+            _ => self.errors.push(err),
+        }
+        if self.errors.len() == self.max_errors {
+            self.errors.push(ParseError::TooManyErrors);
+            return Err(ParseError::TooManyErrors);
+        }
+        Ok(())
+    }
+    fn find_symbol(&self, name: &str) -> Option<&Symbol> {
+        self.symbols
+            .iter()
+            .rev()
+            .find(|(n, _)| n == name)
+            .map(|(_, symbol)| symbol)
+    }
+    fn find_typedef(&self, name: &str) -> Option<&Type> {
+        match self.find_symbol(name) {
+            Some(Symbol::Typedef(ty)) => Some(ty),
+            _ => None,
+        }
+    }
+    // Declares `name` in the innermost scope, where it may only have been
+    // declared before as the same global variable.
+    fn declare(&mut self, name: String, symbol: Symbol, loc: Loc) -> Result<(), ParseError> {
+        let start = self.symbol_scopes.last().copied().unwrap_or(0);
+        let is_redeclaration = |other: &Symbol| match (other, &symbol) {
+            (Symbol::Global(a), Symbol::Global(b)) => a.name == b.name,
+            _ => false,
+        };
+        if self.symbols[start..]
+            .iter()
+            .any(|(n, other)| *n == name && !is_redeclaration(other))
+        {
+            return Err(ParseError::Redefinition(Token::new(
+                TokenKind::Ident(name),
+                loc,
+            )));
+        }
+        self.symbols.push((name, symbol));
+        Ok(())
+    }
+    // Reserves an unnamed slot of 8 bytes, which can hold any scalar. Its
+    // actual type is given by `sema::analyze`. Fails at `tok`, the operator
+    // that needs the slot, if the frame has no room left.
+    fn new_temp(&mut self, tok: &Token) -> Result<LocalVariable, ParseError> {
+        if !self.lvars.has_room_for(&Type::Long) {
+            return Err(ParseError::TooLarge(tok.clone()));
+        }
+        Ok(self.lvars.push(LocalVariable::new("", Type::Long)).clone())
+    }
+    fn find_tag(&self, tag: &str) -> Option<&Tag> {
+        self.tags
+            .iter()
+            .rev()
+            .find(|(t, _)| t == tag)
+            .map(|(_, t)| t)
+    }
+    // Finds `tag` among the tags declared in the innermost scope.
+    fn find_tag_in_scope(&self, tag: &str) -> Option<&Tag> {
+        let start = self.tag_scopes.last().copied().unwrap_or(0);
+        self.tags[start..]
+            .iter()
+            .rev()
+            .find(|(t, _)| t == tag)
+            .map(|(_, t)| t)
+    }
+}
 
 #[derive(Debug)]
 pub enum ParseError {
     Unexpected(Token),
     NotClosingParen(Token),
+    // `break` outside of a loop or switch, or `continue` outside of a loop.
+    NotInLoop(Token),
+    // `case` or `default` outside of a switch.
+    NotInSwitch(Token),
+    DuplicateDefault(Token),
+    // `goto` to a label that the function does not define.
+    UndefinedLabel(Token),
+    // An enumerator whose value is not an integer constant that fits in an
+    // int.
+    NotConstant(Token),
+    TooManyParams(Token),
+    TooManyArgs(Token),
+    UndefinedVariable(Token),
+    Redefinition(Token),
+    // A variable or member whose struct type has no definition yet.
+    IncompleteType(Token),
+    // An array, struct or stack frame larger than `MAX_SIZE`: the length of
+    // the array, the member or variable that does not fit, or the operator
+    // whose temporary does not fit.
+    TooLarge(Token),
     Eof,
+    // The last error when the limit on the number of errors stopped parsing.
+    TooManyErrors,
+}
+
+// Number of errors that `parse` reports at most.
+pub const DEFAULT_ERROR_LIMIT: usize = 20;
+
+pub fn parse(tokens: Vec<Token>) -> Result<Program, Vec<ParseError>> {
+    parse_with_limit(tokens, DEFAULT_ERROR_LIMIT)
+}
+
+// Parses the program, recovering from each error at the end of the
+// statement or declaration that it occurs in, so that all errors are
+// reported. After `max_errors` errors, unless it is 0, parsing stops.
+pub fn parse_with_limit(tokens: Vec<Token>, max_errors: usize) -> Result<Program, Vec<ParseError>> {
+    // Iterating a slice makes cloning the iterator, for lookahead, cheap.
+    let mut tokens = tokens.iter().cloned().peekable();
+    let mut ctx = Context::new();
+    ctx.max_errors = max_errors;
+    let mut program = Program::default();
+    while tokens.peek().is_some() {
+        if let Err(err) = external_decl(&mut tokens, &mut ctx, &mut program) {
+            ctx.leave_all_scopes();
+            if recover(&mut tokens, &mut ctx, err).is_err() {
+                break;
+            }
+            // A `}` without a matching `{` cannot start anything.
+            consume_if(&mut tokens, TokenKind::RBrace);
+        }
+    }
+    if !ctx.errors.is_empty() {
+        return Err(ctx.errors);
+    }
+    program.globals.append(&mut ctx.static_locals);
+    Ok(program)
 }
 
-pub fn parse(tokens: Vec<Token>) -> Result<Vec<Node>, ParseError> {
-    let mut tokens = tokens.into_iter().peekable();
-    let mut code = Vec::<Node>::new();
+// Parses a function definition, or a declaration at file scope.
+fn external_decl<Tokens>(
+    tokens: &mut Peekable<Tokens>,
+    ctx: &mut Context,
+    program: &mut Program,
+) -> Result<(), ParseError>
+where
+    Tokens: Iterator<Item = Token> + Clone,
+{
+    let mut attr = DeclAttr::default();
+    let base_ty = declspec(tokens, ctx, Some(&mut attr))?;
+    // A declaration such as `struct s { ... };` only declares the tag.
+    if consume_if(tokens, TokenKind::Eof) {
+        return Ok(());
+    }
+    let (ty, name, loc) = declarator(tokens, base_ty.clone())?;
+    if !attr.is_typedef && tokens.peek().map(|t| &t.value) == Some(&TokenKind::LParen) {
+        return function(tokens, ctx, program, ty, name, loc, &attr);
+    }
+    let mut decl = (ty, name, loc);
     loop {
-        match tokens.peek().map(|t| &t.value) {
-            Some(TokenKind::Eof) => return Ok(code),
-            Some(_) => code.push(stmt(&mut tokens)?),
-            None => return Ok(code),
+        let (ty, name, loc) = decl;
+        if let Some(global) = global(tokens, ctx, &attr, ty, name, loc)? {
+            program.globals.push(global);
         }
+        if consume_if(tokens, TokenKind::Eof) {
+            return Ok(());
+        }
+        expect(tokens, TokenKind::Comma)?;
+        decl = declarator(tokens, base_ty.clone())?;
     }
 }
 
-fn stmt<Tokens>(tokens: &mut Peekable<Tokens>) -> Result<Node, ParseError>
+// Records `err` and skips the rest of the statement or declaration that it
+// occurred in: up to and including a `;` or a body in braces, but not a `}`
+// that closes an enclosing block.
+fn recover<Tokens>(
+    tokens: &mut Peekable<Tokens>,
+    ctx: &mut Context,
+    err: ParseError,
+) -> Result<(), ParseError>
 where
-    Tokens: Iterator<Item = Token>,
+    Tokens: Iterator<Item = Token> + Clone,
 {
-    let node = match tokens.peek().map(|t| &t.value) {
-        Some(TokenKind::Return) => match tokens.next().unwrap() {
-            Token {
-                value: TokenKind::Return,
-                loc,
-            } => Node::new_return(expr(tokens)?, loc),
-            _ => unreachable!(),
-        },
-        _ => expr(tokens)?,
-    };
-    match tokens.peek() {
-        Some(Token {
-            value: TokenKind::Eof,
-            ..
-        }) => {
-            tokens.next();
-            Ok(node)
+    ctx.report(err)?;
+    let mut depth = 0;
+    while let Some(tok) = tokens.peek() {
+        match tok.value {
+            TokenKind::Eof if depth == 0 => {
+                tokens.next();
+                break;
+            }
+            TokenKind::LBrace => depth += 1,
+            TokenKind::RBrace if depth == 0 => break,
+            TokenKind::RBrace if depth == 1 => {
+                tokens.next();
+                break;
+            }
+            TokenKind::RBrace => depth -= 1,
+            _ => (),
         }
-        _ => Err(ParseError::Eof),
+        tokens.next();
     }
+    Ok(())
 }
 
-fn expr<Tokens>(tokens: &mut Peekable<Tokens>) -> Result<Node, ParseError>
+// Parses the parameters and the body of a function whose declarator has been
+// read, or the `;` that ends a prototype. Parameters of a prototype may be
+// unnamed.
+fn function<Tokens>(
+    tokens: &mut Peekable<Tokens>,
+    ctx: &mut Context,
+    program: &mut Program,
+    ret_ty: Type,
+    name: String,
+    loc: Loc,
+    attr: &DeclAttr,
+) -> Result<(), ParseError>
 where
-    Tokens: Iterator<Item = Token>,
+    Tokens: Iterator<Item = Token> + Clone,
 {
-    assign(tokens)
+    // Qualifiers of the return type have no effect on the value.
+    let ret_ty = ret_ty.unqualified().clone();
+    ctx.lvars = LocalVariables::new();
+    // The parameters are in scope in the body.
+    ctx.enter_scope();
+    expect(tokens, TokenKind::LParen)?;
+    let mut params = Vec::<LocalVariable>::new();
+    while !consume_if(tokens, TokenKind::RParen) {
+        if !params.is_empty() {
+            expect(tokens, TokenKind::Comma)?;
+        }
+        let base_ty = declspec(tokens, ctx, None)?;
+        let (mut ty, param, loc) = param_declarator(tokens, base_ty)?;
+        if let Type::Array(base, _) = ty {
+            ty = Type::pointer_to(*base);
+        }
+        if params.len() == MAX_ARGS {
+            let tok = Token::new(TokenKind::Ident(param), loc);
+            return Err(ParseError::TooManyParams(tok));
+        }
+        if param.is_empty() {
+            params.push(ctx.lvars.push(LocalVariable::new("", ty)).clone());
+        } else {
+            params.push(declare_local_var(param, ty, loc, ctx)?.clone());
+        }
+    }
+    if consume_if(tokens, TokenKind::Eof) {
+        ctx.leave_scope();
+        program.prototypes.push(Prototype {
+            name,
+            ret_ty,
+            params: params.iter().map(|p| p.ty.unqualified().clone()).collect(),
+            loc,
+        });
+        return Ok(());
+    }
+    if program.functions.iter().any(|f| f.name == name) {
+        return Err(ParseError::Redefinition(Token::new(
+            TokenKind::Ident(name),
+            loc,
+        )));
+    }
+    ctx.labels.clear();
+    ctx.gotos.clear();
+    let body = block(tokens, ctx)?;
+    ctx.leave_scope();
+    for (name, loc) in std::mem::take(&mut ctx.gotos) {
+        if !ctx.labels.contains(&name) {
+            let tok = Token::new(TokenKind::Ident(name), loc);
+            ctx.report(ParseError::UndefinedLabel(tok))?;
+        }
+    }
+    program.functions.push(Function {
+        name,
+        ret_ty,
+        params,
+        body,
+        locals: std::mem::replace(&mut ctx.lvars, LocalVariables::new()),
+        is_static: attr.is_static,
+        loc,
+    });
+    Ok(())
 }
 
-fn assign<Tokens>(tokens: &mut Peekable<Tokens>) -> Result<Node, ParseError>
+// Declares a typedef name or a global variable at file scope, whose
+// declarator has been read, and parses the optional initializer of a
+// variable. Only a variable definition, which is not `extern`, is returned.
+fn global<Tokens>(
+    tokens: &mut Peekable<Tokens>,
+    ctx: &mut Context,
+    attr: &DeclAttr,
+    ty: Type,
+    name: String,
+    loc: Loc,
+) -> Result<Option<Global>, ParseError>
 where
-    Tokens: Iterator<Item = Token>,
+    Tokens: Iterator<Item = Token> + Clone,
 {
-    let mut node = equality(tokens)?;
-    node = match tokens.peek().map(|t| &t.value) {
-        Some(TokenKind::ASSIGN) => match tokens.next().unwrap() {
-            Token {
-                value: TokenKind::ASSIGN,
-                loc,
-            } => Node::new_assign(node, assign(tokens)?, loc),
-            _ => unreachable!(),
-        },
-        _ => node,
+    if attr.is_typedef {
+        ctx.declare(name, Symbol::Typedef(ty), loc)?;
+        return Ok(None);
+    }
+    let var = GlobalVariable {
+        name: name.clone(),
+        ty,
     };
-    Ok(node)
+    if attr.is_extern {
+        ctx.declare(name, Symbol::Global(var), loc)?;
+        return Ok(None);
+    }
+    if ctx.globals.iter().any(|g| g.name == name) {
+        return Err(ParseError::Redefinition(Token::new(
+            TokenKind::Ident(name),
+            loc,
+        )));
+    }
+    if !var.ty.is_complete() {
+        return Err(ParseError::IncompleteType(Token::new(
+            TokenKind::Ident(name),
+            loc,
+        )));
+    }
+    ctx.declare(name, Symbol::Global(var.clone()), loc)?;
+    ctx.globals.push(var.clone());
+    let init = if consume_if(tokens, TokenKind::ASSIGN) {
+        Some(initializer(tokens, ctx)?)
+    } else {
+        None
+    };
+    Ok(Some(Global {
+        var,
+        init,
+        is_static: attr.is_static,
+        loc,
+    }))
+}
+
+// Parses `assign | "{" (initializer ("," initializer)* ","?)? "}"`.
+fn initializer<Tokens>(
+    tokens: &mut Peekable<Tokens>,
+    ctx: &mut Context,
+) -> Result<Initializer, ParseError>
+where
+    Tokens: Iterator<Item = Token> + Clone,
+{
+    let loc = match tokens.peek() {
+        Some(Token {
+            value: TokenKind::LBrace,
+            loc,
+        }) => *loc,
+        _ => return Ok(Initializer::Expr(assign(tokens, ctx)?)),
+    };
+    tokens.next();
+    let mut items = Vec::<Initializer>::new();
+    while !consume_if(tokens, TokenKind::RBrace) {
+        if !items.is_empty() {
+            expect(tokens, TokenKind::Comma)?;
+            if consume_if(tokens, TokenKind::RBrace) {
+                break;
+            }
+        }
+        items.push(initializer(tokens, ctx)?);
+    }
+    Ok(Initializer::List(items, loc))
+}
+
+// Parses the specifiers and qualifiers of a declaration. The integer
+// keywords may come in any order, as in `long unsigned int`. Storage
+// classes are only allowed where `attr` can record them.
+fn declspec<Tokens>(
+    tokens: &mut Peekable<Tokens>,
+    ctx: &mut Context,
+    mut attr: Option<&mut DeclAttr>,
+) -> Result<Type, ParseError>
+where
+    Tokens: Iterator<Item = Token> + Clone,
+{
+    // A struct, union, enum or typedef name, which excludes any other type
+    // specifier.
+    let mut base: Option<Type> = None;
+    let mut is_const = false;
+    let (mut chars, mut shorts, mut ints, mut longs) = (0, 0, 0, 0);
+    let (mut signed, mut unsigned) = (0, 0);
+    let (mut floats, mut doubles) = (0, 0);
+    loop {
+        let specifiers = chars + shorts + ints + longs + signed + unsigned + floats + doubles;
+        match tokens.peek().map(|t| &t.value) {
+            Some(TokenKind::Typedef)
+            | Some(TokenKind::Static)
+            | Some(TokenKind::Extern)
+            | Some(TokenKind::Inline) => {
+                let tok = tokens.next().unwrap();
+                let attr = match attr.as_deref_mut() {
+                    Some(attr) => attr,
+                    None => return Err(ParseError::Unexpected(tok)),
+                };
+                match tok.value {
+                    TokenKind::Typedef => attr.is_typedef = true,
+                    TokenKind::Static => attr.is_static = true,
+                    TokenKind::Extern => attr.is_extern = true,
+                    _ => (),
+                }
+                if attr.is_typedef as u8 + attr.is_static as u8 + attr.is_extern as u8 > 1 {
+                    return Err(ParseError::Unexpected(tok));
+                }
+                continue;
+            }
+            Some(TokenKind::Const) => {
+                tokens.next();
+                is_const = true;
+                continue;
+            }
+            Some(TokenKind::Volatile) | Some(TokenKind::Restrict) => {
+                tokens.next();
+                continue;
+            }
+            Some(TokenKind::Struct) | Some(TokenKind::Union) | Some(TokenKind::Enum) => {
+                let tok = tokens.next().unwrap();
+                if base.is_some() || specifiers > 0 {
+                    return Err(ParseError::Unexpected(tok));
+                }
+                base = Some(match tok.value {
+                    TokenKind::Struct => struct_decl(tokens, ctx, StructKind::Struct)?,
+                    TokenKind::Union => struct_decl(tokens, ctx, StructKind::Union)?,
+                    _ => enum_decl(tokens, ctx)?,
+                });
+                continue;
+            }
+            // Any other identifier is the name being declared.
+            Some(TokenKind::Ident(name)) if base.is_none() && specifiers == 0 => {
+                match ctx.find_typedef(name) {
+                    Some(ty) => base = Some(ty.clone()),
+                    None => break,
+                }
+                tokens.next();
+                continue;
+            }
+            Some(TokenKind::CharType) => chars += 1,
+            Some(TokenKind::ShortType) => shorts += 1,
+            Some(TokenKind::IntType) => ints += 1,
+            Some(TokenKind::LongType) => longs += 1,
+            Some(TokenKind::Signed) => signed += 1,
+            Some(TokenKind::Unsigned) => unsigned += 1,
+            Some(TokenKind::FloatType) => floats += 1,
+            Some(TokenKind::DoubleType) => doubles += 1,
+            _ => break,
+        }
+        let tok = tokens.next().unwrap();
+        let sizes = chars + shorts + longs.min(1);
+        let flonums = floats + doubles;
+        if base.is_some() {
+            return Err(ParseError::Unexpected(tok));
+        }
+        if sizes > 1 || longs > 2 || ints > 1 || chars + ints > 1 || signed + unsigned > 1 {
+            return Err(ParseError::Unexpected(tok));
+        }
+        // Only `long double` mixes a floating type with another specifier.
+        if flonums > 1 || (flonums > 0 && sizes + ints + signed + unsigned > longs) {
+            return Err(ParseError::Unexpected(tok));
+        }
+        if floats > 0 && longs > 0 || doubles > 0 && longs > 1 {
+            return Err(ParseError::Unexpected(tok));
+        }
+    }
+    let unsigned = unsigned > 0;
+    let ty = if let Some(ty) = base {
+        ty
+    } else if floats > 0 {
+        Type::Float
+    } else if doubles > 0 {
+        // A `long double` is represented as a double.
+        Type::Double
+    } else if chars > 0 {
+        if unsigned {
+            Type::UChar
+        } else {
+            Type::Char
+        }
+    } else if shorts > 0 {
+        if unsigned {
+            Type::UShort
+        } else {
+            Type::Short
+        }
+    } else if longs > 0 {
+        if unsigned {
+            Type::ULong
+        } else {
+            Type::Long
+        }
+    } else if ints > 0 || signed > 0 || unsigned {
+        if unsigned {
+            Type::UInt
+        } else {
+            Type::Int
+        }
+    } else {
+        return Err(unexpected_or_eof(tokens));
+    };
+    Ok(if is_const { Type::const_of(ty) } else { ty })
+}
+
+fn struct_decl<Tokens>(
+    tokens: &mut Peekable<Tokens>,
+    ctx: &mut Context,
+    kind: StructKind,
+) -> Result<Type, ParseError>
+where
+    Tokens: Iterator<Item = Token> + Clone,
+{
+    let tag = match tokens.peek().map(|t| &t.value) {
+        Some(TokenKind::Ident(_)) => Some(ident(tokens)?),
+        _ => None,
+    };
+    if !consume_if(tokens, TokenKind::LBrace) {
+        let (tag, loc) = match tag {
+            Some(tag) => tag,
+            None => return Err(unexpected_or_eof(tokens)),
+        };
+        // A reference to a tag not yet in scope declares an incomplete type.
+        let st = match ctx.find_tag(&tag) {
+            Some(Tag::Struct(st)) if st.kind == kind => st.clone(),
+            Some(_) => {
+                return Err(ParseError::Redefinition(Token::new(
+                    TokenKind::Ident(tag),
+                    loc,
+                )))
+            }
+            None => {
+                let st = Rc::new(StructType::new(kind, Some(tag.clone())));
+                ctx.tags.push((tag, Tag::Struct(st.clone())));
+                st
+            }
+        };
+        return Ok(Type::Struct(st));
+    }
+
+    // The tag is in scope from the opening brace, so members may point to it.
+    let st = match &tag {
+        Some((tag, loc)) => match ctx.find_tag_in_scope(tag) {
+            Some(Tag::Struct(st)) if st.kind == kind && !Type::Struct(st.clone()).is_complete() => {
+                st.clone()
+            }
+            Some(_) => {
+                let tok = Token::new(TokenKind::Ident(tag.clone()), *loc);
+                return Err(ParseError::Redefinition(tok));
+            }
+            None => {
+                let st = Rc::new(StructType::new(kind, Some(tag.clone())));
+                ctx.tags.push((tag.clone(), Tag::Struct(st.clone())));
+                st
+            }
+        },
+        None => Rc::new(StructType::new(kind, None)),
+    };
+    let mut members = Vec::<(String, Type)>::new();
+    let mut member_toks = Vec::<Token>::new();
+    while !consume_if(tokens, TokenKind::RBrace) {
+        let base_ty = declspec(tokens, ctx, None)?;
+        let mut first = true;
+        while !consume_if(tokens, TokenKind::Eof) {
+            if !first {
+                expect(tokens, TokenKind::Comma)?;
+            }
+            first = false;
+            let (ty, name, loc) = declarator(tokens, base_ty.clone())?;
+            let tok = Token::new(TokenKind::Ident(name.clone()), loc);
+            if !ty.is_complete() {
+                return Err(ParseError::IncompleteType(tok));
+            }
+            if members.iter().any(|(m, _)| *m == name) {
+                return Err(ParseError::Redefinition(tok));
+            }
+            members.push((name, ty));
+            member_toks.push(tok);
+        }
+    }
+    if let Err(i) = st.define(members) {
+        return Err(ParseError::TooLarge(member_toks.swap_remove(i)));
+    }
+    Ok(Type::Struct(st))
+}
+
+// Parses `ident? ("{" enumerator ("," enumerator)* ","? "}")?` after `enum`,
+// where `enumerator` is `ident ("=" const-expr)?`. The enumerators are
+// declared as int constants, each one greater than the previous one unless
+// its value is given. An enum type is represented as int.
+fn enum_decl<Tokens>(tokens: &mut Peekable<Tokens>, ctx: &mut Context) -> Result<Type, ParseError>
+where
+    Tokens: Iterator<Item = Token> + Clone,
+{
+    let tag = match tokens.peek().map(|t| &t.value) {
+        Some(TokenKind::Ident(_)) => Some(ident(tokens)?),
+        _ => None,
+    };
+    if !consume_if(tokens, TokenKind::LBrace) {
+        let (tag, loc) = match tag {
+            Some(tag) => tag,
+            None => return Err(unexpected_or_eof(tokens)),
+        };
+        return match ctx.find_tag(&tag) {
+            Some(Tag::Enum) => Ok(Type::Int),
+            Some(_) => Err(ParseError::Redefinition(Token::new(
+                TokenKind::Ident(tag),
+                loc,
+            ))),
+            None => Err(ParseError::IncompleteType(Token::new(
+                TokenKind::Ident(tag),
+                loc,
+            ))),
+        };
+    }
+    if let Some((tag, loc)) = tag {
+        if ctx.find_tag_in_scope(&tag).is_some() {
+            return Err(ParseError::Redefinition(Token::new(
+                TokenKind::Ident(tag),
+                loc,
+            )));
+        }
+        ctx.tags.push((tag, Tag::Enum));
+    }
+    let mut value = 0;
+    let mut first = true;
+    while !consume_if(tokens, TokenKind::RBrace) {
+        if !first {
+            expect(tokens, TokenKind::Comma)?;
+            if consume_if(tokens, TokenKind::RBrace) {
+                break;
+            }
+        }
+        first = false;
+        let (name, loc) = ident(tokens)?;
+        let tok = Token::new(TokenKind::Ident(name.clone()), loc);
+        if consume_if(tokens, TokenKind::ASSIGN) {
+            // The value is needed now, since each use of the enumerator is
+            // parsed as it, and the next enumerator counts on from it.
+            let mut node = conditional(tokens, ctx)?;
+            value = match sema::eval_int(&mut node) {
+                Ok(n) => n,
+                Err(_) => return Err(ParseError::NotConstant(tok)),
+            };
+        }
+        if value != value as i32 as i64 {
+            return Err(ParseError::NotConstant(tok));
+        }
+        ctx.declare(name, Symbol::EnumConst(value), loc)?;
+        value += 1;
+    }
+    Ok(Type::Int)
+}
+
+// Parses `"*"* ident type-suffix`, deriving the declared type from `ty`.
+fn declarator<Tokens>(
+    tokens: &mut Peekable<Tokens>,
+    ty: Type,
+) -> Result<(Type, String, Loc), ParseError>
+where
+    Tokens: Iterator<Item = Token> + Clone,
+{
+    let ty = pointers(tokens, ty);
+    let (name, loc) = ident(tokens)?;
+    Ok((type_suffix(tokens, ty)?, name, loc))
+}
+
+// Parses a parameter, which is a declarator whose name may be omitted in a
+// prototype. An unnamed parameter has an empty name.
+fn param_declarator<Tokens>(
+    tokens: &mut Peekable<Tokens>,
+    ty: Type,
+) -> Result<(Type, String, Loc), ParseError>
+where
+    Tokens: Iterator<Item = Token> + Clone,
+{
+    let ty = pointers(tokens, ty);
+    match tokens.peek() {
+        Some(Token {
+            value: TokenKind::Ident(_),
+            ..
+        }) => {
+            let (name, loc) = ident(tokens)?;
+            Ok((type_suffix(tokens, ty)?, name, loc))
+        }
+        Some(tok) => {
+            let loc = tok.loc;
+            Ok((type_suffix(tokens, ty)?, String::new(), loc))
+        }
+        None => Err(ParseError::Eof),
+    }
+}
+
+// Parses `"*"* type-suffix`, the declarator of a type name such as `int *[3]`.
+fn abstract_declarator<Tokens>(tokens: &mut Peekable<Tokens>, ty: Type) -> Result<Type, ParseError>
+where
+    Tokens: Iterator<Item = Token> + Clone,
+{
+    let ty = pointers(tokens, ty);
+    type_suffix(tokens, ty)
+}
+
+// Parses `("*" ("const" | "volatile" | "restrict")*)*`, deriving pointer
+// types from `ty`.
+fn pointers<Tokens>(tokens: &mut Peekable<Tokens>, mut ty: Type) -> Type
+where
+    Tokens: Iterator<Item = Token> + Clone,
+{
+    while consume_if(tokens, TokenKind::Mul) {
+        ty = Type::pointer_to(ty);
+        loop {
+            match tokens.peek().map(|t| &t.value) {
+                Some(TokenKind::Const) => ty = Type::const_of(ty),
+                Some(TokenKind::Volatile) | Some(TokenKind::Restrict) => (),
+                _ => break,
+            }
+            tokens.next();
+        }
+    }
+    ty
+}
+
+// Parses `("[" num "]")*`. The leftmost dimension is the outermost array.
+fn type_suffix<Tokens>(tokens: &mut Peekable<Tokens>, ty: Type) -> Result<Type, ParseError>
+where
+    Tokens: Iterator<Item = Token> + Clone,
+{
+    if !consume_if(tokens, TokenKind::LBracket) {
+        return Ok(ty);
+    }
+    let (len, tok) = match tokens.next() {
+        Some(tok) => match tok.value {
+            TokenKind::Int(n, _) if n >= 0 => (n as usize, tok),
+            _ => return Err(ParseError::Unexpected(tok)),
+        },
+        None => return Err(ParseError::Eof),
+    };
+    expect(tokens, TokenKind::RBracket)?;
+    let base = type_suffix(tokens, ty)?;
+    Type::checked_array_of(base, len).ok_or(ParseError::TooLarge(tok))
+}
+
+// Tells whether the next token starts a declaration, which an identifier
+// does if it is a typedef name in scope.
+fn is_typename<Tokens>(tokens: &mut Peekable<Tokens>, ctx: &Context) -> bool
+where
+    Tokens: Iterator<Item = Token> + Clone,
+{
+    match tokens.peek().map(|t| &t.value) {
+        Some(TokenKind::Ident(name)) => ctx.find_typedef(name).is_some(),
+        kind => matches!(
+            kind,
+            Some(TokenKind::CharType)
+                | Some(TokenKind::ShortType)
+                | Some(TokenKind::IntType)
+                | Some(TokenKind::LongType)
+                | Some(TokenKind::FloatType)
+                | Some(TokenKind::DoubleType)
+                | Some(TokenKind::Signed)
+                | Some(TokenKind::Unsigned)
+                | Some(TokenKind::Struct)
+                | Some(TokenKind::Union)
+                | Some(TokenKind::Enum)
+                | Some(TokenKind::Typedef)
+                | Some(TokenKind::Static)
+                | Some(TokenKind::Extern)
+                | Some(TokenKind::Inline)
+                | Some(TokenKind::Const)
+                | Some(TokenKind::Volatile)
+                | Some(TokenKind::Restrict)
+        ),
+    }
+}
+
+// Parses `int a, b = expr;` into a block of the initializing assignments.
+// A block may also declare typedef names, `extern` globals and static
+// locals.
+fn declaration<Tokens>(tokens: &mut Peekable<Tokens>, ctx: &mut Context) -> Result<Node, ParseError>
+where
+    Tokens: Iterator<Item = Token> + Clone,
+{
+    let start = tokens.peek().map(|t| t.loc);
+    let mut attr = DeclAttr::default();
+    let base_ty = declspec(tokens, ctx, Some(&mut attr))?;
+    let mut inits = Vec::<Node>::new();
+    let mut first = true;
+    while !consume_if(tokens, TokenKind::Eof) {
+        if !first {
+            expect(tokens, TokenKind::Comma)?;
+        }
+        first = false;
+        let (ty, name, loc) = declarator(tokens, base_ty.clone())?;
+        if attr.is_typedef {
+            ctx.declare(name, Symbol::Typedef(ty), loc)?;
+            continue;
+        }
+        if attr.is_extern {
+            let var = GlobalVariable {
+                name: name.clone(),
+                ty,
+            };
+            ctx.declare(name, Symbol::Global(var), loc)?;
+            continue;
+        }
+        if attr.is_static {
+            static_local(tokens, ctx, ty, name, loc)?;
+            continue;
+        }
+        let mut var = declare_local_var(name, ty, loc, ctx)?.clone();
+        if let Some(TokenKind::ASSIGN) = tokens.peek().map(|t| &t.value) {
+            let op = tokens.next().unwrap().loc;
+            // The initializer may store to a const variable.
+            var.ty = var.ty.unqualified().clone();
+            let lhs = Node::new_lvar(var, loc);
+            inits.push(Node::new_assign(lhs, assign(tokens, ctx)?, op));
+        }
+    }
+    Ok(Node::new_block(inits, start.unwrap()))
+}
+
+// Declares a static local variable, which is a global under a name unique
+// in the program, and parses its optional initializer.
+fn static_local<Tokens>(
+    tokens: &mut Peekable<Tokens>,
+    ctx: &mut Context,
+    ty: Type,
+    name: String,
+    loc: Loc,
+) -> Result<(), ParseError>
+where
+    Tokens: Iterator<Item = Token> + Clone,
+{
+    if !ty.is_complete() {
+        return Err(ParseError::IncompleteType(Token::new(
+            TokenKind::Ident(name),
+            loc,
+        )));
+    }
+    let var = GlobalVariable {
+        name: format!("{}.{}", name, ctx.static_locals.len()),
+        ty,
+    };
+    ctx.declare(name, Symbol::Global(var.clone()), loc)?;
+    let init = if consume_if(tokens, TokenKind::ASSIGN) {
+        Some(initializer(tokens, ctx)?)
+    } else {
+        None
+    };
+    ctx.static_locals.push(Global {
+        var,
+        init,
+        is_static: true,
+        loc,
+    });
+    Ok(())
+}
+
+fn stmt<Tokens>(tokens: &mut Peekable<Tokens>, ctx: &mut Context) -> Result<Node, ParseError>
+where
+    Tokens: Iterator<Item = Token> + Clone,
+{
+    let node = match tokens.peek().map(|t| &t.value) {
+        Some(TokenKind::LBrace) => return block(tokens, ctx),
+        Some(TokenKind::If) => return if_stmt(tokens, ctx),
+        Some(TokenKind::While) => return while_stmt(tokens, ctx),
+        Some(TokenKind::Do) => do_while_stmt(tokens, ctx)?,
+        Some(TokenKind::For) => return for_stmt(tokens, ctx),
+        Some(TokenKind::Switch) => return switch_stmt(tokens, ctx),
+        Some(TokenKind::Case) | Some(TokenKind::Default) => return labeled_stmt(tokens, ctx),
+        Some(TokenKind::Break) | Some(TokenKind::Continue) => {
+            let tok = tokens.next().unwrap();
+            let in_switch = tok.value == TokenKind::Break && !ctx.switches.is_empty();
+            if ctx.loop_depth == 0 && !in_switch {
+                return Err(ParseError::NotInLoop(tok));
+            }
+            match tok.value {
+                TokenKind::Break => Node::new_break(tok.loc),
+                _ => Node::new_continue(tok.loc),
+            }
+        }
+        Some(TokenKind::Goto) => {
+            let loc = tokens.next().unwrap().loc;
+            let (name, name_loc) = ident(tokens)?;
+            ctx.gotos.push((name.clone(), name_loc));
+            Node::new_goto(name, loc)
+        }
+        Some(TokenKind::Ident(_)) => return labeled_or_expr_stmt(tokens, ctx),
+        Some(TokenKind::Return) => match tokens.next().unwrap() {
+            Token {
+                value: TokenKind::Return,
+                loc,
+            } => Node::new_return(expr(tokens, ctx)?, loc),
+            _ => unreachable!(),
+        },
+        _ => expr(tokens, ctx)?,
+    };
+    expect(tokens, TokenKind::Eof)?;
+    Ok(node)
+}
+
+// Parses `name: stmt`, or an expression statement starting with an
+// identifier, which takes a second token of lookahead to tell apart.
+fn labeled_or_expr_stmt<Tokens>(
+    tokens: &mut Peekable<Tokens>,
+    ctx: &mut Context,
+) -> Result<Node, ParseError>
+where
+    Tokens: Iterator<Item = Token> + Clone,
+{
+    let mut lookahead = tokens.clone();
+    lookahead.next();
+    if lookahead.peek().map(|t| &t.value) != Some(&TokenKind::Colon) {
+        let node = expr(tokens, ctx)?;
+        expect(tokens, TokenKind::Eof)?;
+        return Ok(node);
+    }
+    let (name, loc) = ident(tokens)?;
+    tokens.next();
+    if ctx.labels.contains(&name) {
+        return Err(ParseError::Redefinition(Token::new(
+            TokenKind::Ident(name),
+            loc,
+        )));
+    }
+    ctx.labels.push(name.clone());
+    Ok(Node::new_label(name, stmt(tokens, ctx)?, loc))
+}
+
+fn block<Tokens>(tokens: &mut Peekable<Tokens>, ctx: &mut Context) -> Result<Node, ParseError>
+where
+    Tokens: Iterator<Item = Token> + Clone,
+{
+    let loc = expect(tokens, TokenKind::LBrace)?.loc;
+    ctx.enter_scope();
+    let stmts = block_items(tokens, ctx);
+    ctx.leave_scope();
+    let stmts = stmts?;
+    let end = expect(tokens, TokenKind::RBrace)?.loc;
+    Ok(Node::new_block(stmts, loc.merge(&end)))
+}
+
+fn block_items<Tokens>(
+    tokens: &mut Peekable<Tokens>,
+    ctx: &mut Context,
+) -> Result<Vec<Node>, ParseError>
+where
+    Tokens: Iterator<Item = Token> + Clone,
+{
+    let mut stmts = Vec::<Node>::new();
+    loop {
+        match tokens.peek().map(|t| &t.value) {
+            Some(TokenKind::RBrace) | None => return Ok(stmts),
+            Some(_) => {
+                let stmt = if is_typename(tokens, ctx) {
+                    declaration(tokens, ctx)
+                } else {
+                    stmt(tokens, ctx)
+                };
+                match stmt {
+                    Ok(stmt) => stmts.push(stmt),
+                    Err(err) => recover(tokens, ctx, err)?,
+                }
+            }
+        }
+    }
+}
+
+fn if_stmt<Tokens>(tokens: &mut Peekable<Tokens>, ctx: &mut Context) -> Result<Node, ParseError>
+where
+    Tokens: Iterator<Item = Token> + Clone,
+{
+    let loc = expect(tokens, TokenKind::If)?.loc;
+    expect(tokens, TokenKind::LParen)?;
+    let cond = expr(tokens, ctx)?;
+    expect(tokens, TokenKind::RParen)?;
+    let then = stmt(tokens, ctx)?;
+    let els = match tokens.peek().map(|t| &t.value) {
+        Some(TokenKind::Else) => {
+            tokens.next();
+            Some(stmt(tokens, ctx)?)
+        }
+        _ => None,
+    };
+    Ok(Node::new_if(cond, then, els, loc))
+}
+
+fn while_stmt<Tokens>(tokens: &mut Peekable<Tokens>, ctx: &mut Context) -> Result<Node, ParseError>
+where
+    Tokens: Iterator<Item = Token> + Clone,
+{
+    let loc = expect(tokens, TokenKind::While)?.loc;
+    expect(tokens, TokenKind::LParen)?;
+    let cond = expr(tokens, ctx)?;
+    expect(tokens, TokenKind::RParen)?;
+    let body = loop_body(tokens, ctx)?;
+    Ok(Node::new_while(cond, body, loc))
+}
+
+fn for_stmt<Tokens>(tokens: &mut Peekable<Tokens>, ctx: &mut Context) -> Result<Node, ParseError>
+where
+    Tokens: Iterator<Item = Token> + Clone,
+{
+    let loc = expect(tokens, TokenKind::For)?.loc;
+    expect(tokens, TokenKind::LParen)?;
+    // A variable declared in the first clause is scoped to the loop.
+    ctx.enter_scope();
+    let node = for_clauses(tokens, ctx, loc);
+    ctx.leave_scope();
+    node
+}
+
+fn for_clauses<Tokens>(
+    tokens: &mut Peekable<Tokens>,
+    ctx: &mut Context,
+    loc: Loc,
+) -> Result<Node, ParseError>
+where
+    Tokens: Iterator<Item = Token> + Clone,
+{
+    let init = if is_typename(tokens, ctx) {
+        Some(declaration(tokens, ctx)?)
+    } else {
+        opt_expr(tokens, ctx, TokenKind::Eof)?
+    };
+    let cond = opt_expr(tokens, ctx, TokenKind::Eof)?;
+    let step = opt_expr(tokens, ctx, TokenKind::RParen)?;
+    let body = loop_body(tokens, ctx)?;
+    Ok(Node::new_for(init, cond, step, body, loc))
+}
+
+// Parses `do stmt while (expr)`, leaving the `;` to `stmt`.
+fn do_while_stmt<Tokens>(
+    tokens: &mut Peekable<Tokens>,
+    ctx: &mut Context,
+) -> Result<Node, ParseError>
+where
+    Tokens: Iterator<Item = Token> + Clone,
+{
+    let loc = expect(tokens, TokenKind::Do)?.loc;
+    let body = loop_body(tokens, ctx)?;
+    expect(tokens, TokenKind::While)?;
+    expect(tokens, TokenKind::LParen)?;
+    let cond = expr(tokens, ctx)?;
+    expect(tokens, TokenKind::RParen)?;
+    Ok(Node::new_do_while(body, cond, loc))
+}
+
+fn switch_stmt<Tokens>(tokens: &mut Peekable<Tokens>, ctx: &mut Context) -> Result<Node, ParseError>
+where
+    Tokens: Iterator<Item = Token> + Clone,
+{
+    let loc = expect(tokens, TokenKind::Switch)?.loc;
+    expect(tokens, TokenKind::LParen)?;
+    let cond = expr(tokens, ctx)?;
+    expect(tokens, TokenKind::RParen)?;
+    ctx.switches.push(SwitchLabels::default());
+    let body = stmt(tokens, ctx);
+    let labels = ctx.switches.pop().unwrap();
+    Ok(Node::new_switch(cond, body?, labels, loc))
+}
+
+// Parses `case expr: stmt` or `default: stmt`, recording the label in the
+// innermost switch. The value of `expr` is checked by `sema::analyze`.
+fn labeled_stmt<Tokens>(
+    tokens: &mut Peekable<Tokens>,
+    ctx: &mut Context,
+) -> Result<Node, ParseError>
+where
+    Tokens: Iterator<Item = Token> + Clone,
+{
+    let tok = tokens.next().unwrap();
+    if ctx.switches.is_empty() {
+        return Err(ParseError::NotInSwitch(tok));
+    }
+    if tok.value == TokenKind::Case {
+        let expr = conditional(tokens, ctx)?;
+        expect(tokens, TokenKind::Colon)?;
+        let labels = ctx.switches.last_mut().unwrap();
+        labels.cases.push(SwitchCase { expr, value: 0 });
+        let index = labels.cases.len() - 1;
+        return Ok(Node::new_case(index, stmt(tokens, ctx)?, tok.loc));
+    }
+    expect(tokens, TokenKind::Colon)?;
+    let labels = ctx.switches.last_mut().unwrap();
+    if labels.has_default {
+        return Err(ParseError::DuplicateDefault(tok));
+    }
+    labels.has_default = true;
+    Ok(Node::new_default(stmt(tokens, ctx)?, tok.loc))
+}
+
+// Parses the body of a loop, where `break` and `continue` are allowed.
+fn loop_body<Tokens>(tokens: &mut Peekable<Tokens>, ctx: &mut Context) -> Result<Node, ParseError>
+where
+    Tokens: Iterator<Item = Token> + Clone,
+{
+    ctx.loop_depth += 1;
+    let body = stmt(tokens, ctx);
+    ctx.loop_depth -= 1;
+    body
+}
+
+// Parses an optional expression followed by `terminator`, as in the clauses of `for`.
+fn opt_expr<Tokens>(
+    tokens: &mut Peekable<Tokens>,
+    ctx: &mut Context,
+    terminator: TokenKind,
+) -> Result<Option<Node>, ParseError>
+where
+    Tokens: Iterator<Item = Token> + Clone,
+{
+    let node = match tokens.peek() {
+        Some(tok) if tok.value == terminator => None,
+        _ => Some(expr(tokens, ctx)?),
+    };
+    expect(tokens, terminator)?;
+    Ok(node)
+}
+
+fn expr<Tokens>(tokens: &mut Peekable<Tokens>, ctx: &mut Context) -> Result<Node, ParseError>
+where
+    Tokens: Iterator<Item = Token> + Clone,
+{
+    let mut node = assign(tokens, ctx)?;
+    loop {
+        node = match tokens.peek().map(|t| &t.value) {
+            Some(TokenKind::Comma) => {
+                let loc = tokens.next().unwrap().loc;
+                Node::new_comma(node, assign(tokens, ctx)?, loc)
+            }
+            _ => return Ok(node),
+        };
+    }
+}
+
+fn assign<Tokens>(tokens: &mut Peekable<Tokens>, ctx: &mut Context) -> Result<Node, ParseError>
+where
+    Tokens: Iterator<Item = Token> + Clone,
+{
+    let mut node = conditional(tokens, ctx)?;
+    node = match tokens.peek().map(|t| &t.value) {
+        Some(TokenKind::ASSIGN) => match tokens.next().unwrap() {
+            Token {
+                value: TokenKind::ASSIGN,
+                loc,
+            } => Node::new_assign(node, assign(tokens, ctx)?, loc),
+            _ => unreachable!(),
+        },
+        Some(kind) => match compound_assign_op(kind) {
+            Some(op) => {
+                let tok = tokens.next().unwrap();
+                let rhs = assign(tokens, ctx)?;
+                Node::new_compound_assign(BinOp::new(op, tok.loc), node, rhs, ctx, false, &tok)?
+            }
+            None => node,
+        },
+        None => node,
+    };
+    Ok(node)
+}
+
+// The operator of a compound assignment token such as `+=`.
+fn compound_assign_op(kind: &TokenKind) -> Option<BinOpKind> {
+    match kind {
+        TokenKind::AddAssign => Some(BinOpKind::Add),
+        TokenKind::SubAssign => Some(BinOpKind::Sub),
+        TokenKind::MulAssign => Some(BinOpKind::Mul),
+        TokenKind::QuoAssign => Some(BinOpKind::Quo),
+        TokenKind::RemAssign => Some(BinOpKind::Rem),
+        TokenKind::AndAssign => Some(BinOpKind::BitAnd),
+        TokenKind::OrAssign => Some(BinOpKind::BitOr),
+        TokenKind::XorAssign => Some(BinOpKind::BitXor),
+        TokenKind::ShlAssign => Some(BinOpKind::Shl),
+        TokenKind::ShrAssign => Some(BinOpKind::Shr),
+        _ => None,
+    }
+}
+
+// Parses `++expr` or `--expr` as `expr += 1` or `expr -= 1`, and `expr++` or
+// `expr--` likewise but yielding the old value.
+fn inc_dec(tok: Token, expr: Node, ctx: &mut Context, postfix: bool) -> Result<Node, ParseError> {
+    let op = match tok.value {
+        TokenKind::Inc => BinOp::new_add(tok.loc),
+        TokenKind::Dec => BinOp::new_sub(tok.loc),
+        _ => unreachable!(),
+    };
+    let one = Node::new_int(1, Type::Int, tok.loc);
+    Node::new_compound_assign(op, expr, one, ctx, postfix, &tok)
+}
+
+// Parses `logor ("?" expr ":" conditional)?`.
+fn conditional<Tokens>(tokens: &mut Peekable<Tokens>, ctx: &mut Context) -> Result<Node, ParseError>
+where
+    Tokens: Iterator<Item = Token> + Clone,
+{
+    let cond = logor(tokens, ctx)?;
+    match tokens.peek().map(|t| &t.value) {
+        Some(TokenKind::Question) => {
+            let loc = tokens.next().unwrap().loc;
+            let then = expr(tokens, ctx)?;
+            expect(tokens, TokenKind::Colon)?;
+            let els = conditional(tokens, ctx)?;
+            Ok(Node::new_conditional(cond, then, els, loc))
+        }
+        _ => Ok(cond),
+    }
+}
+
+fn logor<Tokens>(tokens: &mut Peekable<Tokens>, ctx: &mut Context) -> Result<Node, ParseError>
+where
+    Tokens: Iterator<Item = Token> + Clone,
+{
+    let mut node = logand(tokens, ctx)?;
+    loop {
+        node = match tokens.peek().map(|t| &t.value) {
+            Some(TokenKind::LOr) => {
+                let loc = tokens.next().unwrap().loc;
+                Node::new_binop(BinOp::new_logor(loc), node, logand(tokens, ctx)?, loc)
+            }
+            _ => return Ok(node),
+        };
+    }
+}
+
+fn logand<Tokens>(tokens: &mut Peekable<Tokens>, ctx: &mut Context) -> Result<Node, ParseError>
+where
+    Tokens: Iterator<Item = Token> + Clone,
+{
+    let mut node = bitor(tokens, ctx)?;
+    loop {
+        node = match tokens.peek().map(|t| &t.value) {
+            Some(TokenKind::LAnd) => {
+                let loc = tokens.next().unwrap().loc;
+                Node::new_binop(BinOp::new_logand(loc), node, bitor(tokens, ctx)?, loc)
+            }
+            _ => return Ok(node),
+        };
+    }
+}
+
+fn bitor<Tokens>(tokens: &mut Peekable<Tokens>, ctx: &mut Context) -> Result<Node, ParseError>
+where
+    Tokens: Iterator<Item = Token> + Clone,
+{
+    let mut node = bitxor(tokens, ctx)?;
+    loop {
+        node = match tokens.peek().map(|t| &t.value) {
+            Some(TokenKind::Or) => {
+                let loc = tokens.next().unwrap().loc;
+                Node::new_binop(BinOp::new_bitor(loc), node, bitxor(tokens, ctx)?, loc)
+            }
+            _ => return Ok(node),
+        };
+    }
+}
+
+fn bitxor<Tokens>(tokens: &mut Peekable<Tokens>, ctx: &mut Context) -> Result<Node, ParseError>
+where
+    Tokens: Iterator<Item = Token> + Clone,
+{
+    let mut node = bitand(tokens, ctx)?;
+    loop {
+        node = match tokens.peek().map(|t| &t.value) {
+            Some(TokenKind::Xor) => {
+                let loc = tokens.next().unwrap().loc;
+                Node::new_binop(BinOp::new_bitxor(loc), node, bitand(tokens, ctx)?, loc)
+            }
+            _ => return Ok(node),
+        };
+    }
+}
+
+fn bitand<Tokens>(tokens: &mut Peekable<Tokens>, ctx: &mut Context) -> Result<Node, ParseError>
+where
+    Tokens: Iterator<Item = Token> + Clone,
+{
+    let mut node = equality(tokens, ctx)?;
+    loop {
+        node = match tokens.peek().map(|t| &t.value) {
+            Some(TokenKind::Amp) => {
+                let loc = tokens.next().unwrap().loc;
+                Node::new_binop(BinOp::new_bitand(loc), node, equality(tokens, ctx)?, loc)
+            }
+            _ => return Ok(node),
+        };
+    }
 }
 
-fn equality<Tokens>(tokens: &mut Peekable<Tokens>) -> Result<Node, ParseError>
+fn equality<Tokens>(tokens: &mut Peekable<Tokens>, ctx: &mut Context) -> Result<Node, ParseError>
 where
-    Tokens: Iterator<Item = Token>,
+    Tokens: Iterator<Item = Token> + Clone,
 {
-    let mut node = relational(tokens)?;
+    let mut node = relational(tokens, ctx)?;
     loop {
         node = match tokens.peek().map(|t| &t.value) {
             Some(TokenKind::EQ) | Some(TokenKind::NEQ) => match tokens.next().unwrap() {
                 Token {
                     value: TokenKind::EQ,
                     loc,
-                } => Node::new_binop(BinOp::new_eq(loc), node, relational(tokens)?, loc),
+                } => Node::new_binop(BinOp::new_eq(loc), node, relational(tokens, ctx)?, loc),
                 Token {
                     value: TokenKind::NEQ,
                     loc,
-                } => Node::new_binop(BinOp::new_neq(loc), node, relational(tokens)?, loc),
+                } => Node::new_binop(BinOp::new_neq(loc), node, relational(tokens, ctx)?, loc),
                 _ => unreachable!(),
             },
             _ => return Ok(node),
@@ -226,11 +2000,11 @@ where
     }
 }
 
-fn relational<Tokens>(tokens: &mut Peekable<Tokens>) -> Result<Node, ParseError>
+fn relational<Tokens>(tokens: &mut Peekable<Tokens>, ctx: &mut Context) -> Result<Node, ParseError>
 where
-    Tokens: Iterator<Item = Token>,
+    Tokens: Iterator<Item = Token> + Clone,
 {
-    let mut node = add(tokens)?;
+    let mut node = shift(tokens, ctx)?;
     loop {
         node = match tokens.peek().map(|t| &t.value) {
             Some(TokenKind::LEQ) | Some(TokenKind::GEQ) | Some(TokenKind::LSS)
@@ -238,19 +2012,19 @@ where
                 Token {
                     value: TokenKind::LEQ,
                     loc,
-                } => Node::new_binop(BinOp::new_leq(loc), node, add(tokens)?, loc),
+                } => Node::new_binop(BinOp::new_leq(loc), node, shift(tokens, ctx)?, loc),
                 Token {
                     value: TokenKind::GEQ,
                     loc,
-                } => Node::new_binop(BinOp::new_leq(loc), add(tokens)?, node, loc),
+                } => Node::new_binop(BinOp::new_leq(loc), shift(tokens, ctx)?, node, loc),
                 Token {
                     value: TokenKind::LSS,
                     loc,
-                } => Node::new_binop(BinOp::new_lss(loc), node, add(tokens)?, loc),
+                } => Node::new_binop(BinOp::new_lss(loc), node, shift(tokens, ctx)?, loc),
                 Token {
                     value: TokenKind::GRT,
                     loc,
-                } => Node::new_binop(BinOp::new_lss(loc), add(tokens)?, node, loc),
+                } => Node::new_binop(BinOp::new_lss(loc), shift(tokens, ctx)?, node, loc),
                 _ => unreachable!(),
             },
             _ => return Ok(node),
@@ -258,22 +2032,22 @@ where
     }
 }
 
-fn add<Tokens>(tokens: &mut Peekable<Tokens>) -> Result<Node, ParseError>
+fn shift<Tokens>(tokens: &mut Peekable<Tokens>, ctx: &mut Context) -> Result<Node, ParseError>
 where
-    Tokens: Iterator<Item = Token>,
+    Tokens: Iterator<Item = Token> + Clone,
 {
-    let mut node = mul(tokens)?;
+    let mut node = add(tokens, ctx)?;
     loop {
         node = match tokens.peek().map(|t| &t.value) {
-            Some(TokenKind::Add) | Some(TokenKind::Sub) => match tokens.next().unwrap() {
+            Some(TokenKind::Shl) | Some(TokenKind::Shr) => match tokens.next().unwrap() {
                 Token {
-                    value: TokenKind::Add,
+                    value: TokenKind::Shl,
                     loc,
-                } => Node::new_binop(BinOp::new_add(loc), node, mul(tokens)?, loc),
+                } => Node::new_binop(BinOp::new_shl(loc), node, add(tokens, ctx)?, loc),
                 Token {
-                    value: TokenKind::Sub,
+                    value: TokenKind::Shr,
                     loc,
-                } => Node::new_binop(BinOp::new_sub(loc), node, mul(tokens)?, loc),
+                } => Node::new_binop(BinOp::new_shr(loc), node, add(tokens, ctx)?, loc),
                 _ => unreachable!(),
             },
             _ => return Ok(node),
@@ -281,22 +2055,22 @@ where
     }
 }
 
-fn mul<Tokens>(tokens: &mut Peekable<Tokens>) -> Result<Node, ParseError>
+fn add<Tokens>(tokens: &mut Peekable<Tokens>, ctx: &mut Context) -> Result<Node, ParseError>
 where
-    Tokens: Iterator<Item = Token>,
+    Tokens: Iterator<Item = Token> + Clone,
 {
-    let mut node = unary(tokens)?;
+    let mut node = mul(tokens, ctx)?;
     loop {
         node = match tokens.peek().map(|t| &t.value) {
-            Some(TokenKind::Mul) | Some(TokenKind::Quo) => match tokens.next().unwrap() {
+            Some(TokenKind::Add) | Some(TokenKind::Sub) => match tokens.next().unwrap() {
                 Token {
-                    value: TokenKind::Mul,
+                    value: TokenKind::Add,
                     loc,
-                } => Node::new_binop(BinOp::new_mul(loc), node, unary(tokens)?, loc),
+                } => Node::new_binop(BinOp::new_add(loc), node, mul(tokens, ctx)?, loc),
                 Token {
-                    value: TokenKind::Quo,
+                    value: TokenKind::Sub,
                     loc,
-                } => Node::new_binop(BinOp::new_quo(loc), node, unary(tokens)?, loc),
+                } => Node::new_binop(BinOp::new_sub(loc), node, mul(tokens, ctx)?, loc),
                 _ => unreachable!(),
             },
             _ => return Ok(node),
@@ -304,79 +2078,1017 @@ where
     }
 }
 
-fn unary<Tokens>(tokens: &mut Peekable<Tokens>) -> Result<Node, ParseError>
+fn mul<Tokens>(tokens: &mut Peekable<Tokens>, ctx: &mut Context) -> Result<Node, ParseError>
+where
+    Tokens: Iterator<Item = Token> + Clone,
+{
+    let mut node = unary(tokens, ctx)?;
+    loop {
+        node = match tokens.peek().map(|t| &t.value) {
+            Some(TokenKind::Mul) | Some(TokenKind::Quo) | Some(TokenKind::Rem) => {
+                match tokens.next().unwrap() {
+                    Token {
+                        value: TokenKind::Mul,
+                        loc,
+                    } => Node::new_binop(BinOp::new_mul(loc), node, unary(tokens, ctx)?, loc),
+                    Token {
+                        value: TokenKind::Quo,
+                        loc,
+                    } => Node::new_binop(BinOp::new_quo(loc), node, unary(tokens, ctx)?, loc),
+                    Token {
+                        value: TokenKind::Rem,
+                        loc,
+                    } => Node::new_binop(BinOp::new_rem(loc), node, unary(tokens, ctx)?, loc),
+                    _ => unreachable!(),
+                }
+            }
+            _ => return Ok(node),
+        };
+    }
+}
+
+fn unary<Tokens>(tokens: &mut Peekable<Tokens>, ctx: &mut Context) -> Result<Node, ParseError>
 where
-    Tokens: Iterator<Item = Token>,
+    Tokens: Iterator<Item = Token> + Clone,
 {
     match tokens.peek().map(|t| &t.value) {
-        Some(TokenKind::Add) | Some(TokenKind::Sub) => match tokens.next().unwrap() {
+        Some(TokenKind::Add)
+        | Some(TokenKind::Sub)
+        | Some(TokenKind::Amp)
+        | Some(TokenKind::Mul)
+        | Some(TokenKind::Not)
+        | Some(TokenKind::Tilde) => match tokens.next().unwrap() {
             Token {
                 value: TokenKind::Add,
                 ..
-            } => primary(tokens),
+            } => unary(tokens, ctx),
             Token {
                 value: TokenKind::Sub,
                 loc,
-            } => Ok(Node::new_binop(
-                BinOp::new_sub(loc),
-                Node::new_int(0, loc),
-                primary(tokens)?,
+            } => Ok(Node::new_uniop(
+                UniOp::new_minus(loc),
+                unary(tokens, ctx)?,
+                loc,
+            )),
+            Token {
+                value: TokenKind::Amp,
+                loc,
+            } => Ok(Node::new_uniop(
+                UniOp::new_addr(loc),
+                unary(tokens, ctx)?,
+                loc,
+            )),
+            Token {
+                value: TokenKind::Mul,
+                loc,
+            } => Ok(Node::new_uniop(
+                UniOp::new_deref(loc),
+                unary(tokens, ctx)?,
+                loc,
+            )),
+            Token {
+                value: TokenKind::Not,
+                loc,
+            } => Ok(Node::new_uniop(
+                UniOp::new_not(loc),
+                unary(tokens, ctx)?,
+                loc,
+            )),
+            Token {
+                value: TokenKind::Tilde,
+                loc,
+            } => Ok(Node::new_uniop(
+                UniOp::new_bitnot(loc),
+                unary(tokens, ctx)?,
                 loc,
             )),
             _ => unreachable!(),
         },
-        _ => primary(tokens),
+        Some(TokenKind::Inc) | Some(TokenKind::Dec) => {
+            let tok = tokens.next().unwrap();
+            let expr = unary(tokens, ctx)?;
+            inc_dec(tok, expr, ctx, false)
+        }
+        Some(TokenKind::Sizeof) => sizeof(tokens, ctx),
+        Some(TokenKind::Alignof) => alignof(tokens, ctx),
+        _ => postfix(tokens, ctx),
+    }
+}
+
+fn sizeof<Tokens>(tokens: &mut Peekable<Tokens>, ctx: &mut Context) -> Result<Node, ParseError>
+where
+    Tokens: Iterator<Item = Token> + Clone,
+{
+    let loc = expect(tokens, TokenKind::Sizeof)?.loc;
+    if !consume_if(tokens, TokenKind::LParen) {
+        return Ok(Node::new_sizeof(unary(tokens, ctx)?, loc));
+    }
+    if is_typename(tokens, ctx) {
+        let ty = type_name(tokens, ctx)?;
+        return Ok(Node::new_int(ty.size() as i64, Type::ULong, loc));
+    }
+    let node = expr(tokens, ctx)?;
+    expect(tokens, TokenKind::RParen)?;
+    Ok(Node::new_sizeof(postfix_tail(tokens, ctx, node)?, loc))
+}
+
+// Parses `_Alignof "(" type-name ")"`.
+fn alignof<Tokens>(tokens: &mut Peekable<Tokens>, ctx: &mut Context) -> Result<Node, ParseError>
+where
+    Tokens: Iterator<Item = Token> + Clone,
+{
+    let loc = expect(tokens, TokenKind::Alignof)?.loc;
+    expect(tokens, TokenKind::LParen)?;
+    let ty = type_name(tokens, ctx)?;
+    Ok(Node::new_int(ty.align() as i64, Type::ULong, loc))
+}
+
+// Parses a complete type name followed by the closing parenthesis.
+fn type_name<Tokens>(tokens: &mut Peekable<Tokens>, ctx: &mut Context) -> Result<Type, ParseError>
+where
+    Tokens: Iterator<Item = Token> + Clone,
+{
+    let base_ty = declspec(tokens, ctx, None)?;
+    let ty = abstract_declarator(tokens, base_ty)?;
+    let tok = expect(tokens, TokenKind::RParen)?;
+    if !ty.is_complete() {
+        return Err(ParseError::IncompleteType(tok));
+    }
+    Ok(ty)
+}
+
+fn postfix<Tokens>(tokens: &mut Peekable<Tokens>, ctx: &mut Context) -> Result<Node, ParseError>
+where
+    Tokens: Iterator<Item = Token> + Clone,
+{
+    let node = primary(tokens, ctx)?;
+    postfix_tail(tokens, ctx, node)
+}
+
+// Applies the subscripts and member accesses following `node`, turning
+// `a[i]` into `*(a + i)` and `p->m` into `(*p).m`.
+fn postfix_tail<Tokens>(
+    tokens: &mut Peekable<Tokens>,
+    ctx: &mut Context,
+    mut node: Node,
+) -> Result<Node, ParseError>
+where
+    Tokens: Iterator<Item = Token> + Clone,
+{
+    loop {
+        node = match tokens.peek().map(|t| &t.value) {
+            Some(TokenKind::LBracket) => {
+                let loc = tokens.next().unwrap().loc;
+                let index = expr(tokens, ctx)?;
+                expect(tokens, TokenKind::RBracket)?;
+                let addr = Node::new_binop(BinOp::new_add(loc), node, index, loc);
+                Node::new_uniop(UniOp::new_deref(loc), addr, loc)
+            }
+            Some(TokenKind::Dot) => {
+                tokens.next();
+                let (name, loc) = ident(tokens)?;
+                Node::new_member(node, name, loc)
+            }
+            Some(TokenKind::Arrow) => {
+                let arrow = tokens.next().unwrap().loc;
+                let (name, loc) = ident(tokens)?;
+                let deref = Node::new_uniop(UniOp::new_deref(arrow), node, arrow);
+                Node::new_member(deref, name, loc)
+            }
+            Some(TokenKind::Inc) | Some(TokenKind::Dec) => {
+                let tok = tokens.next().unwrap();
+                inc_dec(tok, node, ctx, true)?
+            }
+            _ => return Ok(node),
+        };
     }
 }
 
-fn primary<Tokens>(tokens: &mut Peekable<Tokens>) -> Result<Node, ParseError>
+fn primary<Tokens>(tokens: &mut Peekable<Tokens>, ctx: &mut Context) -> Result<Node, ParseError>
 where
-    Tokens: Iterator<Item = Token>,
+    Tokens: Iterator<Item = Token> + Clone,
 {
     match tokens.peek().map(|t| &t.value) {
-        Some(TokenKind::LParen) => consume_parens(tokens),
+        Some(TokenKind::LParen) => consume_parens(tokens, ctx),
         Some(TokenKind::Ident(_)) => match tokens.next().unwrap() {
             Token {
                 value: TokenKind::Ident(s),
                 loc,
-            } => Ok(find_or_create_local_var(&s, loc)),
+            } => match tokens.peek().map(|t| &t.value) {
+                Some(TokenKind::LParen) => funcall(tokens, ctx, s, loc),
+                _ => find_var(s, loc, ctx),
+            },
+            _ => unreachable!(),
+        },
+        Some(TokenKind::Int(..)) => match tokens.next().unwrap() {
+            Token {
+                value: TokenKind::Int(n, ty),
+                loc,
+            } => Ok(Node::new_int(n, ty, loc)),
+            _ => unreachable!(),
+        },
+        Some(TokenKind::Float(..)) => match tokens.next().unwrap() {
+            Token {
+                value: TokenKind::Float(f, ty),
+                loc,
+            } => Ok(Node::new_float(f, ty, loc)),
             _ => unreachable!(),
         },
-        Some(TokenKind::Int(_)) => match tokens.next().unwrap() {
+        Some(TokenKind::Str(_)) => match tokens.next().unwrap() {
             Token {
-                value: TokenKind::Int(n),
+                value: TokenKind::Str(bytes),
                 loc,
-            } => Ok(Node::new_int(n, loc)),
+            } => Ok(Node::new_str(bytes, loc)),
             _ => unreachable!(),
         },
-        _ => Err(ParseError::Unexpected(tokens.next().unwrap())),
+        _ => Err(unexpected_or_eof(tokens)),
+    }
+}
+
+fn funcall<Tokens>(
+    tokens: &mut Peekable<Tokens>,
+    ctx: &mut Context,
+    name: String,
+    loc: Loc,
+) -> Result<Node, ParseError>
+where
+    Tokens: Iterator<Item = Token> + Clone,
+{
+    expect(tokens, TokenKind::LParen)?;
+    let mut args = Vec::<Node>::new();
+    while !consume_if(tokens, TokenKind::RParen) {
+        if !args.is_empty() {
+            expect(tokens, TokenKind::Comma)?;
+        }
+        args.push(assign(tokens, ctx)?);
     }
+    if args.len() > MAX_ARGS {
+        let tok = Token::new(TokenKind::Ident(name), loc);
+        return Err(ParseError::TooManyArgs(tok));
+    }
+    Ok(Node::new_funcall(name, args, loc))
 }
 
-fn consume_parens<Tokens>(tokens: &mut Peekable<Tokens>) -> Result<Node, ParseError>
+fn consume_parens<Tokens>(
+    tokens: &mut Peekable<Tokens>,
+    ctx: &mut Context,
+) -> Result<Node, ParseError>
 where
-    Tokens: Iterator<Item = Token>,
+    Tokens: Iterator<Item = Token> + Clone,
 {
     tokens.next();
-    let node = expr(tokens)?;
-    match tokens.peek().map(|t| &t.value) {
-        Some(TokenKind::RParen) => {
+    let node = expr(tokens, ctx)?;
+    match tokens.peek() {
+        Some(tok) if tok.value == TokenKind::RParen => {
             tokens.next();
             Ok(node)
         }
-        _ => Err(ParseError::NotClosingParen(tokens.next().unwrap())),
+        Some(tok) => Err(ParseError::NotClosingParen(tok.clone())),
+        None => Err(ParseError::Eof),
     }
 }
 
-fn find_or_create_local_var(s: &str, loc: Loc) -> Node {
-    LVARS.with(|lvars| {
-        let mut lvars = lvars.borrow_mut();
-        match lvars.find_and_get_offset(&s) {
-            Some(offset) => Node::new_lvar(offset, loc),
-            None => {
-                let offset = lvars.push(LocalVariable::new(&s));
-                Node::new_lvar(offset, loc)
+// Resolves `s` to the variable or enum constant that it names in scope.
+fn find_var(s: String, loc: Loc, ctx: &mut Context) -> Result<Node, ParseError> {
+    match ctx.find_symbol(&s) {
+        Some(Symbol::Local) => Ok(Node::new_lvar(ctx.lvars.find(&s).unwrap().clone(), loc)),
+        Some(Symbol::Global(var)) => Ok(Node::new_gvar(var.clone(), loc)),
+        Some(Symbol::EnumConst(n)) => Ok(Node::new_int(*n, Type::Int, loc)),
+        Some(Symbol::Typedef(_)) => {
+            Err(ParseError::Unexpected(Token::new(TokenKind::Ident(s), loc)))
+        }
+        None => Err(ParseError::UndefinedVariable(Token::new(
+            TokenKind::Ident(s),
+            loc,
+        ))),
+    }
+}
+
+fn declare_local_var(
+    s: String,
+    ty: Type,
+    loc: Loc,
+    ctx: &mut Context,
+) -> Result<&LocalVariable, ParseError> {
+    if !ty.is_complete() {
+        return Err(ParseError::IncompleteType(Token::new(
+            TokenKind::Ident(s),
+            loc,
+        )));
+    }
+    if !ctx.lvars.has_room_for(&ty) {
+        return Err(ParseError::TooLarge(Token::new(TokenKind::Ident(s), loc)));
+    }
+    ctx.declare(s.clone(), Symbol::Local, loc)?;
+    Ok(ctx.lvars.push(LocalVariable::new(&s, ty)))
+}
+
+// The error for a next token that cannot be parsed. The token is left for
+// error recovery, which may resume at it.
+fn unexpected_or_eof<Tokens>(tokens: &mut Peekable<Tokens>) -> ParseError
+where
+    Tokens: Iterator<Item = Token> + Clone,
+{
+    match tokens.peek() {
+        Some(tok) => ParseError::Unexpected(tok.clone()),
+        None => ParseError::Eof,
+    }
+}
+
+fn ident<Tokens>(tokens: &mut Peekable<Tokens>) -> Result<(String, Loc), ParseError>
+where
+    Tokens: Iterator<Item = Token> + Clone,
+{
+    match tokens.peek().map(|t| &t.value) {
+        Some(TokenKind::Ident(_)) => match tokens.next().unwrap() {
+            Token {
+                value: TokenKind::Ident(name),
+                loc,
+            } => Ok((name, loc)),
+            _ => unreachable!(),
+        },
+        _ => Err(unexpected_or_eof(tokens)),
+    }
+}
+
+// Consumes the next token if it is of `kind`, and tells whether it did.
+fn consume_if<Tokens>(tokens: &mut Peekable<Tokens>, kind: TokenKind) -> bool
+where
+    Tokens: Iterator<Item = Token> + Clone,
+{
+    match tokens.peek() {
+        Some(tok) if tok.value == kind => {
+            tokens.next();
+            true
+        }
+        _ => false,
+    }
+}
+
+fn expect<Tokens>(tokens: &mut Peekable<Tokens>, kind: TokenKind) -> Result<Token, ParseError>
+where
+    Tokens: Iterator<Item = Token> + Clone,
+{
+    match tokens.peek() {
+        Some(tok) if tok.value == kind => Ok(tokens.next().unwrap()),
+        _ => Err(unexpected_or_eof(tokens)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    // Parses `tokens`, keeping only the first error.
+    fn parse_first_error(tokens: Vec<Token>) -> Result<Program, ParseError> {
+        parse(tokens).map_err(|mut errors| errors.remove(0))
+    }
+
+    #[test]
+    fn test_local_variable_shadowing() {
+        let offset = |lvars: &LocalVariables, s| lvars.find(s).map(|v| v.offset);
+        let mut lvars = LocalVariables::new();
+        let a = lvars.push(LocalVariable::new("a", Type::Int)).offset;
+        lvars.enter_scope();
+        assert_eq!(offset(&lvars, "a"), Some(a));
+        let inner = lvars.push(LocalVariable::new("a", Type::Int)).offset;
+        let b = lvars.push(LocalVariable::new("b", Type::Int)).offset;
+        assert_ne!(a, inner);
+        assert_eq!(offset(&lvars, "a"), Some(inner));
+        assert_eq!(offset(&lvars, "b"), Some(b));
+        lvars.leave_scope();
+        assert_eq!(offset(&lvars, "a"), Some(a));
+        assert_eq!(offset(&lvars, "b"), None);
+    }
+
+    #[test]
+    fn test_stack_size() {
+        let mut lvars = LocalVariables::new();
+        assert_eq!(lvars.stack_size(), 0);
+        for (i, name) in ["a", "b", "c", "d"].iter().enumerate() {
+            let var = lvars.push(LocalVariable::new(name, Type::Int));
+            assert_eq!(var.offset, (i as i32 + 1) * 4);
+        }
+        assert_eq!(lvars.stack_size(), 16);
+        lvars.push(LocalVariable::new("e", Type::Int));
+        assert_eq!(lvars.stack_size(), 32);
+    }
+
+    #[test]
+    fn test_locals_are_per_function() {
+        let tokens =
+            lex("int f() { int a = 1; int b = 2; return a; } int main() { int c = 3; return c; }")
+                .unwrap();
+        let program = parse(tokens).unwrap();
+        assert_eq!(program.functions[0].locals.iter().count(), 2);
+        assert_eq!(program.functions[0].locals.stack_size(), 16);
+        assert_eq!(program.functions[1].locals.iter().count(), 1);
+        assert_eq!(program.functions[1].locals.stack_size(), 16);
+    }
+
+    #[test]
+    fn test_block() {
+        let tokens = lex("int main() { int a; { int b; } }").unwrap();
+        let program = parse(tokens).unwrap();
+        assert_eq!(program.functions.len(), 1);
+        match &program.functions[0].body.value {
+            NodeKind::Block(stmts) => {
+                assert_eq!(stmts.len(), 2);
+                match &stmts[1].value {
+                    NodeKind::Block(inner) => assert_eq!(inner.len(), 1),
+                    _ => panic!(),
+                }
+            }
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn test_undefined_variable() {
+        let tokens = lex("int main() { int a; { int b; } return b; }").unwrap();
+        match parse_first_error(tokens) {
+            Err(ParseError::UndefinedVariable(tok)) => {
+                assert_eq!(
+                    tok,
+                    Token::new(TokenKind::Ident("b".to_string()), Loc(38, 39))
+                )
+            }
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn test_redefinition() {
+        let tokens = lex("int main() { int a; { int a; } int a; }").unwrap();
+        match parse_first_error(tokens) {
+            Err(ParseError::Redefinition(tok)) => {
+                assert_eq!(
+                    tok,
+                    Token::new(TokenKind::Ident("a".to_string()), Loc(35, 36))
+                )
+            }
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn test_pointer_declarator() {
+        let tokens = lex("int **f(int *p) { int a, *b, **c; return c; }").unwrap();
+        let program = parse(tokens).unwrap();
+        let int_ptr = Type::pointer_to(Type::Int);
+        assert_eq!(
+            program.functions[0].ret_ty,
+            Type::pointer_to(int_ptr.clone())
+        );
+        assert_eq!(program.functions[0].params[0].ty, int_ptr);
+        let types: Vec<_> = program.functions[0]
+            .locals
+            .iter()
+            .map(|v| v.ty.clone())
+            .collect();
+        assert_eq!(
+            types,
+            vec![
+                int_ptr.clone(),
+                Type::Int,
+                int_ptr.clone(),
+                Type::pointer_to(int_ptr)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_array_declarator() {
+        let tokens = lex("int main() { int a[2][3], *b[4]; return sizeof(int *[5]); }").unwrap();
+        let program = parse(tokens).unwrap();
+        let types: Vec<_> = program.functions[0]
+            .locals
+            .iter()
+            .map(|v| v.ty.clone())
+            .collect();
+        assert_eq!(
+            types,
+            vec![
+                Type::array_of(Type::array_of(Type::Int, 3), 2),
+                Type::array_of(Type::pointer_to(Type::Int), 4)
+            ]
+        );
+        let offsets: Vec<_> = program.functions[0]
+            .locals
+            .iter()
+            .map(|v| v.offset)
+            .collect();
+        assert_eq!(offsets, vec![24, 56]);
+        assert_eq!(program.functions[0].locals.stack_size(), 64);
+        match &program.functions[0].body.value {
+            NodeKind::Block(stmts) => match &stmts[1].value {
+                NodeKind::Return(node) => assert_eq!(node.value, NodeKind::Int(40)),
+                _ => panic!(),
+            },
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn test_function() {
+        let tokens =
+            lex("int add(int a, int b) { return a + b; } int main() { return 0; }").unwrap();
+        let program = parse(tokens).unwrap();
+        assert_eq!(program.functions.len(), 2);
+        assert_eq!(program.functions[0].name, "add");
+        assert_eq!(program.functions[0].ret_ty, Type::Int);
+        let params: Vec<_> = program.functions[0]
+            .params
+            .iter()
+            .map(|p| (&p.name[..], p.offset))
+            .collect();
+        assert_eq!(params, vec![("a", 4), ("b", 8)]);
+        assert_eq!(program.functions[1].name, "main");
+        assert!(program.functions[1].params.is_empty());
+    }
+
+    #[test]
+    fn test_float_types() {
+        let tokens =
+            lex("double sqrt(double); float f(float x, double *); long double g;").unwrap();
+        let program = parse(tokens).unwrap();
+        assert!(program.functions.is_empty());
+        let protos: Vec<_> = program
+            .prototypes
+            .iter()
+            .map(|p| (&p.name[..], &p.ret_ty, &p.params[..]))
+            .collect();
+        let double_ptr = Type::pointer_to(Type::Double);
+        assert_eq!(
+            protos,
+            vec![
+                ("sqrt", &Type::Double, &[Type::Double][..]),
+                ("f", &Type::Float, &[Type::Float, double_ptr][..]),
+            ]
+        );
+        assert_eq!(program.globals[0].var.ty, Type::Double);
+        for src in &[
+            "float double x;",
+            "long float x;",
+            "unsigned double x;",
+            "int float x;",
+        ] {
+            assert!(parse(lex(src).unwrap()).is_err(), "{}", src);
+        }
+    }
+
+    #[test]
+    fn test_global_variables() {
+        let tokens = lex("int x, *y = &x; int main() { int x; return x + *y; } int z[2];").unwrap();
+        let program = parse(tokens).unwrap();
+        let globals: Vec<_> = program
+            .globals
+            .iter()
+            .map(|g| (&g.var.name[..], g.var.ty.clone(), g.init.is_some()))
+            .collect();
+        assert_eq!(
+            globals,
+            vec![
+                ("x", Type::Int, false),
+                ("y", Type::pointer_to(Type::Int), true),
+                ("z", Type::array_of(Type::Int, 2), false)
+            ]
+        );
+        // The local `x` shadows the global one, while `y` resolves to the global.
+        let stmts = match &program.functions[0].body.value {
+            NodeKind::Block(stmts) => stmts,
+            _ => panic!(),
+        };
+        match &stmts[1].value {
+            NodeKind::Return(node) => match &node.value {
+                NodeKind::BinOp { lhs, rhs, .. } => {
+                    assert!(matches!(lhs.value, NodeKind::LocalVariable(_)));
+                    match &rhs.value {
+                        NodeKind::UniOp { expr, .. } => {
+                            assert!(matches!(expr.value, NodeKind::GlobalVariable(_)))
+                        }
+                        _ => panic!(),
+                    }
+                }
+                _ => panic!(),
+            },
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn test_global_redefinition() {
+        let tokens = lex("int x; char x;").unwrap();
+        match parse_first_error(tokens) {
+            Err(ParseError::Redefinition(tok)) => assert_eq!(tok.loc, Loc(12, 13)),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn test_function_redefinition() {
+        let tokens = lex("int f(); int f() { return 1; } int f(); int f() { return 2; }").unwrap();
+        match parse_first_error(tokens) {
+            Err(ParseError::Redefinition(tok)) => assert_eq!(tok.loc, Loc(44, 45)),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn test_struct_declarations() {
+        let tokens = lex("struct s { char a; int b; }; int main() { struct s x; { struct s { int c; } y; } struct s *p; return 0; }").unwrap();
+        let program = parse(tokens).unwrap();
+        let types: Vec<_> = program.functions[0]
+            .locals
+            .iter()
+            .map(|v| v.ty.clone())
+            .collect();
+        // The inner definition is a different type that shadows the outer tag.
+        assert_eq!(types[0].size(), 8);
+        assert_eq!(types[0].member("b").unwrap().offset, 4);
+        assert_eq!(types[1].size(), 4);
+        assert_ne!(types[0], types[1]);
+        assert_eq!(types[2], Type::pointer_to(types[0].clone()));
+    }
+
+    #[test]
+    fn test_incomplete_type() {
+        let tokens =
+            lex("struct s *p; struct s { struct s *next; int a; } x; struct t y;").unwrap();
+        match parse_first_error(tokens) {
+            Err(ParseError::IncompleteType(tok)) => {
+                assert_eq!(tok.value, TokenKind::Ident("y".to_string()))
+            }
+            _ => panic!(),
+        }
+        let tokens = lex("struct s { int a; }; struct s { int b; };").unwrap();
+        match parse_first_error(tokens) {
+            Err(ParseError::Redefinition(tok)) => {
+                assert_eq!(tok.value, TokenKind::Ident("s".to_string()))
+            }
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn test_too_large() {
+        for (input, loc) in &[
+            ("int a[1000000000];", Loc(6, 16)),
+            ("char a[3000000000];", Loc(7, 17)),
+            ("char a[3][4][134217728];", Loc(7, 8)),
+            (
+                "struct s { char a[1000000000]; char b[100000000]; };",
+                Loc(36, 37),
+            ),
+            (
+                "int main() { char a[1000000000]; char b[100000000]; }",
+                Loc(38, 39),
+            ),
+            ("int main() { char a[1073741824]; a[0]++; }", Loc(37, 39)),
+            ("int main() { char a[1073741824]; a[0] += 1; }", Loc(38, 40)),
+        ] {
+            match parse_first_error(lex(input).unwrap()) {
+                Err(ParseError::TooLarge(tok)) => assert_eq!(tok.loc, *loc, "{}", input),
+                r => panic!("{}: {:?}", input, r),
+            }
+        }
+        let tokens = lex("char a[1073741824]; int main() { char b[1073741824]; }").unwrap();
+        assert!(parse(tokens).is_ok());
+    }
+
+    // Renders an expression with every operation parenthesized.
+    fn show(node: &Node) -> String {
+        match &node.value {
+            NodeKind::Int(n) => n.to_string(),
+            NodeKind::LocalVariable(var) => var.name.clone(),
+            NodeKind::Assign { lhs, rhs } => format!("({} = {})", show(lhs), show(rhs)),
+            NodeKind::UniOp { op, expr } => format!("({:?} {})", op.value, show(expr)),
+            NodeKind::BinOp { op, lhs, rhs } => {
+                format!("({} {:?} {})", show(lhs), op.value, show(rhs))
+            }
+            NodeKind::Conditional { cond, then, els } => {
+                format!("({} ? {} : {})", show(cond), show(then), show(els))
+            }
+            NodeKind::Comma { lhs, rhs } => format!("({}, {})", show(lhs), show(rhs)),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn test_operator_precedence() {
+        let cases = vec![
+            (
+                "a = b, c ? d : e ? f : g",
+                "((a = b), (c ? d : (e ? f : g)))",
+            ),
+            (
+                "a || b && c | d ^ e & f == g",
+                "(a LogOr (b LogAnd (c BitOr (d BitXor (e BitAnd (f EQ g))))))",
+            ),
+            ("a < b << c + d % e", "(a LSS (b Shl (c Add (d Rem e))))"),
+            ("!~a >> 1 << 2", "(((Not (BitNot a)) Shr 1) Shl 2)"),
+            ("a = b ? c , d : e", "(a = (b ? (c, d) : e))"),
+        ];
+        for (input, expected) in cases {
+            let src = format!(
+                "int main() {{ int a, b, c, d, e, f, g; return {}; }}",
+                input
+            );
+            let program = parse(lex(&src).unwrap()).unwrap();
+            let stmts = match &program.functions[0].body.value {
+                NodeKind::Block(stmts) => stmts,
+                _ => panic!(),
+            };
+            match &stmts.last().unwrap().value {
+                NodeKind::Return(node) => assert_eq!(show(node), expected, "{}", input),
+                _ => panic!(),
+            }
+        }
+        assert!(parse(lex("int main() { return 1 ? 2; }").unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_switch() {
+        let tokens =
+            lex("int main() { switch (1) { case 1: case 2: break; default: break; } }").unwrap();
+        let program = parse(tokens).unwrap();
+        let (body, cases, has_default) = match &program.functions[0].body.value {
+            NodeKind::Block(stmts) => match &stmts[0].value {
+                NodeKind::Switch {
+                    body,
+                    cases,
+                    has_default,
+                    ..
+                } => (body, cases, *has_default),
+                _ => panic!(),
+            },
+            _ => panic!(),
+        };
+        assert_eq!(cases.len(), 2);
+        assert!(has_default);
+        match &body.value {
+            NodeKind::Block(stmts) => match &stmts[0].value {
+                NodeKind::Case { index: 0, stmt } => {
+                    assert!(matches!(stmt.value, NodeKind::Case { index: 1, .. }))
+                }
+                _ => panic!(),
+            },
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn test_invalid_switch() {
+        let tokens = lex("int main() { case 1: return 0; }").unwrap();
+        match parse_first_error(tokens) {
+            Err(ParseError::NotInSwitch(tok)) => assert_eq!(tok.loc, Loc(13, 17)),
+            r => panic!("{:?}", r),
+        }
+        let tokens = lex("int main() { switch (1) { default: default: break; } }").unwrap();
+        match parse_first_error(tokens) {
+            Err(ParseError::DuplicateDefault(tok)) => assert_eq!(tok.loc, Loc(35, 42)),
+            r => panic!("{:?}", r),
+        }
+        let tokens = lex("int main() { switch (1) continue; }").unwrap();
+        match parse_first_error(tokens) {
+            Err(ParseError::NotInLoop(tok)) => assert_eq!(tok.value, TokenKind::Continue),
+            r => panic!("{:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_labels() {
+        let tokens = lex("int main() { int a; a: goto b; b: a = 1; return a; }").unwrap();
+        let program = parse(tokens).unwrap();
+        match &program.functions[0].body.value {
+            NodeKind::Block(stmts) => {
+                match &stmts[1].value {
+                    NodeKind::Label { name, stmt } => {
+                        assert_eq!(name, "a");
+                        assert_eq!(stmt.value, NodeKind::Goto("b".to_string()));
+                    }
+                    _ => panic!(),
+                }
+                assert!(matches!(stmts[2].value, NodeKind::Label { .. }));
+            }
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn test_invalid_labels() {
+        let tokens = lex("int main() { goto a; b: return 0; }").unwrap();
+        match parse_first_error(tokens) {
+            Err(ParseError::UndefinedLabel(tok)) => assert_eq!(
+                tok,
+                Token::new(TokenKind::Ident("a".to_string()), Loc(18, 19))
+            ),
+            r => panic!("{:?}", r),
+        }
+        let tokens = lex("int main() { a: goto a; a: return 0; }").unwrap();
+        match parse_first_error(tokens) {
+            Err(ParseError::Redefinition(tok)) => assert_eq!(tok.loc, Loc(24, 25)),
+            r => panic!("{:?}", r),
+        }
+        // Labels are local to their function.
+        let tokens = lex("int f() { a: return 0; } int main() { goto a; }").unwrap();
+        match parse_first_error(tokens) {
+            Err(ParseError::UndefinedLabel(tok)) => assert_eq!(tok.loc, Loc(43, 44)),
+            r => panic!("{:?}", r),
+        }
+    }
+
+    // Finds a local variable of `func` by name, whether or not it is in scope.
+    fn local<'a>(func: &'a Function, name: &str) -> &'a LocalVariable {
+        func.locals.iter().find(|v| v.name == name).unwrap()
+    }
+
+    #[test]
+    fn test_typedef() {
+        // A typedef name starts a declaration unless a variable shadows it.
+        let tokens = lex("typedef int t; int main() { t a; { int t; t = 1; } return a; }").unwrap();
+        let program = parse(tokens).unwrap();
+        assert_eq!(local(&program.functions[0], "a").ty, Type::Int);
+        let tokens = lex("int main() { typedef char *s; s p; return sizeof(s); }").unwrap();
+        let program = parse(tokens).unwrap();
+        let p = local(&program.functions[0], "p");
+        assert_eq!(p.ty, Type::pointer_to(Type::Char));
+        let tokens = lex("typedef int t; int main() { return t; }").unwrap();
+        match parse_first_error(tokens) {
+            Err(ParseError::Unexpected(tok)) => assert_eq!(tok.loc, Loc(35, 36)),
+            r => panic!("{:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_enum() {
+        let tokens = lex("enum e { A, B = 5, C }; int main() { enum e x; return C; }").unwrap();
+        let program = parse(tokens).unwrap();
+        assert_eq!(local(&program.functions[0], "x").ty, Type::Int);
+        match &program.functions[0].body.value {
+            NodeKind::Block(stmts) => match &stmts[1].value {
+                NodeKind::Return(node) => assert_eq!(node.value, NodeKind::Int(6)),
+                _ => panic!(),
+            },
+            _ => panic!(),
+        }
+        let tokens = lex("int main() { int a; enum { A = a }; return 0; }").unwrap();
+        match parse_first_error(tokens) {
+            Err(ParseError::NotConstant(tok)) => assert_eq!(tok.loc, Loc(27, 28)),
+            r => panic!("{:?}", r),
+        }
+        let tokens = lex("int main() { enum e x; return 0; }").unwrap();
+        match parse_first_error(tokens) {
+            Err(ParseError::IncompleteType(tok)) => assert_eq!(tok.loc, Loc(18, 19)),
+            r => panic!("{:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_qualifiers() {
+        let tokens = lex("int main() { const int *const p; volatile int v; return 0; }").unwrap();
+        let program = parse(tokens).unwrap();
+        let ty = Type::const_of(Type::pointer_to(Type::const_of(Type::Int)));
+        assert_eq!(local(&program.functions[0], "p").ty, ty);
+        assert_eq!(local(&program.functions[0], "v").ty, Type::Int);
+    }
+
+    #[test]
+    fn test_storage_classes() {
+        let tokens =
+            lex("static int g; int f() { static int n; extern int e; return n + e; }").unwrap();
+        let program = parse(tokens).unwrap();
+        assert!(program.globals[0].is_static);
+        assert_eq!(program.globals[1].var.name, "n.0");
+        assert_eq!(program.functions[0].locals.iter().count(), 0);
+        // Storage classes are not allowed in a parameter or a type name.
+        let tokens = lex("int f(static int a) { return a; }").unwrap();
+        match parse_first_error(tokens) {
+            Err(ParseError::Unexpected(tok)) => assert_eq!(tok.value, TokenKind::Static),
+            r => panic!("{:?}", r),
+        }
+        let tokens = lex("int main() { static extern int a; return 0; }").unwrap();
+        match parse_first_error(tokens) {
+            Err(ParseError::Unexpected(tok)) => assert_eq!(tok.value, TokenKind::Extern),
+            r => panic!("{:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_error_recovery() {
+        let locs = |errors: &[ParseError]| -> Vec<Option<Loc>> {
+            errors
+                .iter()
+                .map(|e| match e {
+                    ParseError::Unexpected(tok)
+                    | ParseError::UndefinedVariable(tok)
+                    | ParseError::UndefinedLabel(tok) => Some(tok.loc),
+                    _ => None,
+                })
+                .collect()
+        };
+        // Parsing resumes after the `;` that ends a statement.
+        let tokens = lex("int main() { int x = ; y = 1; return z; }").unwrap();
+        let errors = parse(tokens).unwrap_err();
+        let expected = vec![Some(Loc(21, 22)), Some(Loc(23, 24)), Some(Loc(37, 38))];
+        assert_eq!(locs(&errors), expected);
+        // A body in braces is skipped whole, and a `}` ends the enclosing
+        // block rather than being skipped.
+        let tokens = lex("int f() { return 1 } int g( { x; } int main() { goto a; }").unwrap();
+        let errors = parse(tokens).unwrap_err();
+        let expected = vec![Some(Loc(19, 20)), Some(Loc(28, 29)), Some(Loc(53, 54))];
+        assert_eq!(locs(&errors), expected);
+        // The end of the input is reported once, however many blocks it ends.
+        let tokens = lex("int main() { if (1) { while (1) { x").unwrap();
+        let errors = parse(tokens).unwrap_err();
+        assert_eq!(locs(&errors), vec![Some(Loc(34, 35)), None]);
+        assert!(matches!(errors[1], ParseError::Eof));
+    }
+
+    #[test]
+    fn test_error_limit() {
+        let input = "int main() { a; b; c; return 0; }";
+        let errors = parse_with_limit(lex(input).unwrap(), 2).unwrap_err();
+        assert_eq!(errors.len(), 3);
+        assert!(matches!(errors[2], ParseError::TooManyErrors));
+        let errors = parse_with_limit(lex(input).unwrap(), 3).unwrap_err();
+        assert_eq!(errors.len(), 4);
+        // A limit of 0 means no limit.
+        let errors = parse_with_limit(lex(input).unwrap(), 0).unwrap_err();
+        assert_eq!(errors.len(), 3);
+    }
+
+    #[test]
+    fn test_funcall() {
+        let tokens = lex("int main() { return add(1, f()); }").unwrap();
+        let program = parse(tokens).unwrap();
+        let stmts = match &program.functions[0].body.value {
+            NodeKind::Block(stmts) => stmts,
+            _ => panic!(),
+        };
+        match &stmts[0].value {
+            NodeKind::Return(node) => match &node.value {
+                NodeKind::FunCall { name, args } => {
+                    assert_eq!(name, "add");
+                    assert_eq!(args.len(), 2);
+                    assert_eq!(args[0].value, NodeKind::Int(1));
+                }
+                _ => panic!(),
+            },
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn test_too_many_args() {
+        let tokens = lex("int main() { return f(1, 2, 3, 4, 5, 6, 7); }").unwrap();
+        match parse_first_error(tokens) {
+            Err(ParseError::TooManyArgs(tok)) => {
+                assert_eq!(tok.value, TokenKind::Ident("f".to_string()))
+            }
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn test_too_many_params() {
+        let tokens =
+            lex("int f(int a, int b, int c, int d, int e, int f, int g) { return 0; }").unwrap();
+        match parse_first_error(tokens) {
+            Err(ParseError::TooManyParams(tok)) => {
+                assert_eq!(tok.value, TokenKind::Ident("g".to_string()))
             }
+            _ => panic!(),
+        }
+    }
+
+    // Words of which random programs are made, to get past the lexer and
+    // deep into the grammar.
+    const WORDS: &[&str] = &[
+        "int", "char", "long", "unsigned", "double", "struct", "union", "enum", "typedef", "const",
+        "static", "extern", "if", "else", "while", "do", "for", "switch", "case", "default",
+        "break", "continue", "goto", "return", "sizeof", "a", "b", "f", "s", "main", "0", "1",
+        "2.5", "\"x\"", "(", ")", "{", "}", "[", "]", ";", ",", ":", "?", ".", "->", "=", "+=",
+        "++", "--", "+", "-", "*", "/", "%", "&", "&&", "|", "||", "^", "~", "!", "<", "<<", "<=",
+        "==", "!=",
+    ];
+
+    proptest! {
+        // Any sequence of tokens is either parsed or rejected with errors.
+        #[test]
+        fn test_parse_is_total(words in prop::collection::vec(prop::sample::select(WORDS), 0..60)) {
+            let tokens = lex(&words.join(" ")).unwrap();
+            let _ = parse(tokens);
         }
-    })
+        #[test]
+        fn test_parse_program_is_total(
+            prefix in prop::collection::vec(prop::sample::select(WORDS), 0..30),
+            body in prop::collection::vec(prop::sample::select(WORDS), 0..30),
+        ) {
+            // A function around the words gets statements parsed.
+            let input = format!("{} int main() {{ {} }}", prefix.join(" "), body.join(" "));
+            let _ = parse(lex(&input).unwrap());
+        }
+    }
 }
@@ -0,0 +1,1613 @@
+use super::lexer::*;
+use super::parser::*;
+use super::types::*;
+use std::collections::HashMap;
+
+#[derive(Debug)]
+pub enum SemaErrorKind {
+    NotLvalue,
+    NotPointer(Type),
+    InvalidOperands(Type, Type),
+    // The operand of a unary operator, or a condition, of the wrong type.
+    InvalidOperand(Type),
+    NotStruct(Type),
+    NoSuchMember(String),
+    // An initializer of a global or a `case` label that cannot be computed
+    // at compile time.
+    NotConstant,
+    // A `case` label with the same value as an earlier one in its switch.
+    DuplicateCase(i64),
+    // An initializer whose shape does not match the type it initializes.
+    InvalidInitializer(Type),
+    // An assignment to an object declared `const`.
+    ReadOnly,
+    // A struct passed to or returned from a function, which the generator
+    // cannot do since it passes every value in a register.
+    StructByValue(Type),
+}
+
+pub type SemaError = Annot<SemaErrorKind>;
+
+impl SemaError {
+    fn not_lvalue(loc: Loc) -> Self {
+        SemaError::new(SemaErrorKind::NotLvalue, loc)
+    }
+    fn not_pointer(ty: Type, loc: Loc) -> Self {
+        SemaError::new(SemaErrorKind::NotPointer(ty), loc)
+    }
+    fn invalid_operands(lhs: Type, rhs: Type, loc: Loc) -> Self {
+        SemaError::new(SemaErrorKind::InvalidOperands(lhs, rhs), loc)
+    }
+    fn invalid_operand(ty: Type, loc: Loc) -> Self {
+        SemaError::new(SemaErrorKind::InvalidOperand(ty), loc)
+    }
+    fn not_struct(ty: Type, loc: Loc) -> Self {
+        SemaError::new(SemaErrorKind::NotStruct(ty), loc)
+    }
+    fn no_such_member(name: String, loc: Loc) -> Self {
+        SemaError::new(SemaErrorKind::NoSuchMember(name), loc)
+    }
+    fn not_constant(loc: Loc) -> Self {
+        SemaError::new(SemaErrorKind::NotConstant, loc)
+    }
+    fn duplicate_case(value: i64, loc: Loc) -> Self {
+        SemaError::new(SemaErrorKind::DuplicateCase(value), loc)
+    }
+    fn invalid_initializer(ty: Type, loc: Loc) -> Self {
+        SemaError::new(SemaErrorKind::InvalidInitializer(ty), loc)
+    }
+    fn read_only(loc: Loc) -> Self {
+        SemaError::new(SemaErrorKind::ReadOnly, loc)
+    }
+    fn struct_by_value(ty: Type, loc: Loc) -> Self {
+        SemaError::new(SemaErrorKind::StructByValue(ty), loc)
+    }
+}
+
+struct Context {
+    // Return and parameter types of the functions declared in the program.
+    funcs: HashMap<String, (Type, Vec<Type>)>,
+    // Return type of the function being analyzed.
+    ret_ty: Type,
+}
+
+// Resolves the type of every expression in the program and checks that
+// the expressions are used consistently with their types. Operands are
+// converted explicitly where C converts them implicitly, and the
+// initializers of globals are evaluated into their static data.
+pub fn analyze(program: &mut Program) -> Result<(), SemaError> {
+    let mut ctx = Context {
+        funcs: program
+            .prototypes
+            .iter()
+            .map(|p| (p.name.clone(), (p.ret_ty.clone(), p.params.clone())))
+            .chain(program.functions.iter().map(|f| {
+                let params = f
+                    .params
+                    .iter()
+                    .map(|p| p.ty.unqualified().clone())
+                    .collect();
+                (f.name.clone(), (f.ret_ty.clone(), params))
+            }))
+            .collect(),
+        ret_ty: Type::Int,
+    };
+    // A function whose return or parameter type is a struct is reported at
+    // its name, the first in the source if there are several.
+    let signatures = program
+        .prototypes
+        .iter()
+        .map(|p| (&p.ret_ty, p.params.clone(), p.loc))
+        .chain(program.functions.iter().map(|f| {
+            let params = f.params.iter().map(|p| p.ty.clone()).collect();
+            (&f.ret_ty, params, f.loc)
+        }));
+    let by_value = signatures
+        .filter_map(|(ret_ty, params, loc)| {
+            let ty = std::iter::once(ret_ty)
+                .chain(params.iter())
+                .find(|ty| !ty.is_scalar())?;
+            Some(SemaError::struct_by_value(ty.unqualified().clone(), loc))
+        })
+        .min_by_key(|err| err.loc.0);
+    if let Some(err) = by_value {
+        return Err(err);
+    }
+    for global in program.globals.iter_mut() {
+        if let Some(init) = global.init.take() {
+            let mut data = StaticData {
+                bytes: vec![0; global.var.ty.size() as usize],
+                relocs: Vec::<Reloc>::new(),
+            };
+            write_init(&global.var.ty, init, 0, &mut data, &ctx)?;
+            global.init = Some(Initializer::Data(data));
+        }
+    }
+    for func in program.functions.iter_mut() {
+        ctx.ret_ty = func.ret_ty.clone();
+        add_type(&mut func.body, &ctx)?;
+    }
+    Ok(())
+}
+
+// Evaluates an integer constant expression, such as the value of an
+// enumerator, while the program is still being parsed.
+pub fn eval_int(node: &mut Node) -> Result<i64, SemaError> {
+    let ctx = Context {
+        funcs: HashMap::new(),
+        ret_ty: Type::Int,
+    };
+    add_type(node, &ctx)?;
+    match eval(node) {
+        Ok((None, n)) if node.ty.as_ref().unwrap().is_integer() => Ok(n),
+        _ => Err(SemaError::not_constant(node.loc)),
+    }
+}
+
+// The types of expressions are unqualified: a const object has the same
+// value as any other. Qualifiers only matter to assignments, which check
+// the declared types through `is_const_lvalue`.
+fn add_type(node: &mut Node, ctx: &Context) -> Result<(), SemaError> {
+    if let NodeKind::CompoundAssign { .. } = node.value {
+        return lower_compound_assign(node, ctx);
+    }
+    node.ty = match &mut node.value {
+        NodeKind::Int(_) | NodeKind::Float(_) => node.ty.clone(),
+        NodeKind::Str(bytes) => Some(Type::array_of(Type::Char, bytes.len() + 1)),
+        NodeKind::LocalVariable(var) => Some(var.ty.unqualified().clone()),
+        NodeKind::GlobalVariable(var) => Some(var.ty.unqualified().clone()),
+        NodeKind::Assign { lhs, rhs } => {
+            add_type(lhs, ctx)?;
+            add_type(rhs, ctx)?;
+            if !is_lvalue(lhs) || matches!(lhs.ty, Some(Type::Array(..))) {
+                return Err(SemaError::not_lvalue(lhs.loc));
+            }
+            if is_const_lvalue(lhs) {
+                return Err(SemaError::read_only(lhs.loc));
+            }
+            // A struct is assigned only from a struct of the same type.
+            let (lhs_ty, rhs_ty) = (lhs.ty.clone().unwrap(), rhs.ty.clone().unwrap());
+            let is_struct = |ty: &Type| matches!(ty, Type::Struct(_));
+            if (is_struct(&lhs_ty) || is_struct(&rhs_ty)) && lhs_ty != rhs_ty {
+                return Err(SemaError::invalid_operands(lhs_ty, rhs_ty, node.loc));
+            }
+            if is_mixed_flonum(&lhs_ty, &rhs_ty) {
+                return Err(SemaError::invalid_operands(lhs_ty, rhs_ty, node.loc));
+            }
+            if lhs_ty.is_numeric() && rhs_ty.is_numeric() {
+                cast(rhs, &lhs_ty);
+            }
+            Some(lhs_ty)
+        }
+        NodeKind::CompoundAssign { .. } => unreachable!(),
+        NodeKind::FunCall { name, args } => {
+            let (ret_ty, params) = match ctx.funcs.get(name) {
+                Some((ret_ty, params)) => (ret_ty.clone(), &params[..]),
+                // A function that is not declared, such as one from libc,
+                // is assumed to return int.
+                None => (Type::Int, &[][..]),
+            };
+            for (i, arg) in args.iter_mut().enumerate() {
+                add_type(arg, ctx)?;
+                let arg_ty = arg.ty.clone().unwrap();
+                if !arg_ty.is_scalar() {
+                    return Err(SemaError::struct_by_value(arg_ty, arg.loc));
+                }
+                match params.get(i) {
+                    Some(param_ty) if is_mixed_flonum(param_ty, &arg_ty) => {
+                        return Err(SemaError::invalid_operands(
+                            param_ty.clone(),
+                            arg_ty,
+                            arg.loc,
+                        ));
+                    }
+                    Some(param_ty) if param_ty.is_numeric() && arg_ty.is_numeric() => {
+                        cast(arg, param_ty)
+                    }
+                    // Without a parameter type, a float is passed as a double.
+                    None if arg_ty == Type::Float => cast(arg, &Type::Double),
+                    _ => (),
+                }
+            }
+            Some(ret_ty)
+        }
+        NodeKind::UniOp { op, expr } => {
+            add_type(expr, ctx)?;
+            match op.value {
+                UniOpKind::Minus => {
+                    let ty = expr.ty.clone().unwrap();
+                    if !ty.is_numeric() {
+                        return Err(SemaError::invalid_operand(ty, expr.loc));
+                    }
+                    let ty = int_promote(&ty);
+                    cast(expr, &ty);
+                    Some(ty)
+                }
+                UniOpKind::Addr => {
+                    if !is_lvalue(expr) {
+                        return Err(SemaError::not_lvalue(expr.loc));
+                    }
+                    Some(Type::pointer_to(expr.ty.clone().unwrap()))
+                }
+                UniOpKind::Deref => match expr.ty.as_ref().unwrap().base() {
+                    Some(base) => Some(base.unqualified().clone()),
+                    None => return Err(SemaError::not_pointer(expr.ty.clone().unwrap(), expr.loc)),
+                },
+                UniOpKind::Not => {
+                    check_scalar(expr)?;
+                    Some(Type::Int)
+                }
+                UniOpKind::BitNot => {
+                    let ty = expr.ty.clone().unwrap();
+                    if !ty.is_integer() {
+                        return Err(SemaError::invalid_operand(ty, expr.loc));
+                    }
+                    let ty = int_promote(&ty);
+                    cast(expr, &ty);
+                    Some(ty)
+                }
+            }
+        }
+        NodeKind::Member { expr, name, offset } => {
+            add_type(expr, ctx)?;
+            let ty = expr.ty.clone().unwrap();
+            if !matches!(ty, Type::Struct(_)) {
+                return Err(SemaError::not_struct(ty, node.loc));
+            }
+            if !is_lvalue(expr) {
+                return Err(SemaError::not_lvalue(expr.loc));
+            }
+            let member = match ty.member(name) {
+                Some(member) => member,
+                None => return Err(SemaError::no_such_member(name.clone(), node.loc)),
+            };
+            *offset = member.offset;
+            Some(member.ty.unqualified().clone())
+        }
+        NodeKind::Sizeof(expr) => {
+            add_type(expr, ctx)?;
+            node.value = NodeKind::Int(expr.ty.as_ref().unwrap().size() as i64);
+            Some(Type::ULong)
+        }
+        NodeKind::Cast(expr) => {
+            add_type(expr, ctx)?;
+            node.ty.as_ref().map(|ty| ty.unqualified().clone())
+        }
+        NodeKind::BinOp { op, lhs, rhs } => {
+            add_type(lhs, ctx)?;
+            add_type(rhs, ctx)?;
+            let (lhs_ty, rhs_ty) = (lhs.ty.clone().unwrap(), rhs.ty.clone().unwrap());
+            match op.value {
+                // Each operand is only tested for zero.
+                BinOpKind::LogAnd | BinOpKind::LogOr => {
+                    check_scalar(lhs)?;
+                    check_scalar(rhs)?;
+                }
+                // The operands of a shift are promoted separately, and the
+                // result has the type of the left one.
+                BinOpKind::Shl | BinOpKind::Shr if lhs_ty.is_integer() && rhs_ty.is_integer() => {
+                    cast(lhs, &int_promote(&lhs_ty));
+                    cast(rhs, &int_promote(&rhs_ty));
+                }
+                _ if lhs_ty.is_numeric() && rhs_ty.is_numeric() => {
+                    let ty = usual_arith_conv(&lhs_ty, &rhs_ty);
+                    cast(lhs, &ty);
+                    cast(rhs, &ty);
+                }
+                _ => (),
+            }
+            Some(binop_type(
+                op,
+                lhs.ty.as_ref().unwrap(),
+                rhs.ty.as_ref().unwrap(),
+            )?)
+        }
+        NodeKind::Conditional { cond, then, els } => {
+            add_type(cond, ctx)?;
+            add_type(then, ctx)?;
+            add_type(els, ctx)?;
+            check_scalar(cond)?;
+            let (then_ty, els_ty) = (then.ty.clone().unwrap(), els.ty.clone().unwrap());
+            if then_ty.is_numeric() && els_ty.is_numeric() {
+                let ty = usual_arith_conv(&then_ty, &els_ty);
+                cast(then, &ty);
+                cast(els, &ty);
+                Some(ty)
+            } else {
+                // Arrays decay to pointers, so arrays of different lengths
+                // still have a common type.
+                let decay = |ty: &Type| match ty {
+                    Type::Array(base, _) => Type::pointer_to((**base).clone()),
+                    _ => ty.clone(),
+                };
+                let (then_ty, els_ty) = (decay(&then_ty), decay(&els_ty));
+                if then_ty != els_ty {
+                    return Err(SemaError::invalid_operands(then_ty, els_ty, node.loc));
+                }
+                Some(then_ty)
+            }
+        }
+        NodeKind::Comma { lhs, rhs } => {
+            add_type(lhs, ctx)?;
+            add_type(rhs, ctx)?;
+            rhs.ty.clone()
+        }
+        NodeKind::Return(expr) => {
+            add_type(expr, ctx)?;
+            let ty = expr.ty.clone().unwrap();
+            if !ty.is_scalar() {
+                return Err(SemaError::struct_by_value(ty, expr.loc));
+            }
+            if is_mixed_flonum(&ctx.ret_ty, &ty) {
+                return Err(SemaError::invalid_operands(
+                    ctx.ret_ty.clone(),
+                    ty,
+                    expr.loc,
+                ));
+            }
+            if ctx.ret_ty.is_numeric() && ty.is_numeric() {
+                cast(expr, &ctx.ret_ty);
+            }
+            None
+        }
+        NodeKind::If { cond, then, els } => {
+            add_type(cond, ctx)?;
+            add_type(then, ctx)?;
+            if let Some(els) = els {
+                add_type(els, ctx)?;
+            }
+            None
+        }
+        NodeKind::Block(stmts) => {
+            for stmt in stmts.iter_mut() {
+                add_type(stmt, ctx)?;
+            }
+            None
+        }
+        NodeKind::While { cond, body } => {
+            add_type(cond, ctx)?;
+            add_type(body, ctx)?;
+            None
+        }
+        NodeKind::DoWhile { body, cond } => {
+            add_type(body, ctx)?;
+            add_type(cond, ctx)?;
+            None
+        }
+        NodeKind::For {
+            init,
+            cond,
+            step,
+            body,
+        } => {
+            for node in [init, cond, step].iter_mut().filter_map(|n| n.as_mut()) {
+                add_type(node, ctx)?;
+            }
+            add_type(body, ctx)?;
+            None
+        }
+        NodeKind::Switch {
+            cond, body, cases, ..
+        } => {
+            add_type(cond, ctx)?;
+            let ty = cond.ty.as_ref().unwrap();
+            if !ty.is_integer() {
+                return Err(SemaError::invalid_operand(ty.clone(), cond.loc));
+            }
+            let ty = int_promote(ty);
+            cast(cond, &ty);
+            for i in 0..cases.len() {
+                let expr = &mut cases[i].expr;
+                add_type(expr, ctx)?;
+                let value = match eval(expr) {
+                    Ok((None, n)) if expr.ty.as_ref().unwrap().is_integer() => convert(n, &ty),
+                    _ => return Err(SemaError::not_constant(expr.loc)),
+                };
+                if cases[..i].iter().any(|case| case.value == value) {
+                    return Err(SemaError::duplicate_case(value, cases[i].expr.loc));
+                }
+                cases[i].value = value;
+            }
+            add_type(body, ctx)?;
+            None
+        }
+        NodeKind::Case { stmt, .. } | NodeKind::Default(stmt) | NodeKind::Label { stmt, .. } => {
+            add_type(stmt, ctx)?;
+            None
+        }
+        NodeKind::Break | NodeKind::Continue | NodeKind::Goto(_) => None,
+    };
+    Ok(())
+}
+
+// Rewrites `lhs op= rhs` into `addr = &lhs, *addr = *addr op rhs`, so that
+// `lhs` is evaluated once and the operands are converted as for `op`. A
+// postfix `lhs++` also saves the value in `old` and yields it:
+// `addr = &lhs, old = *addr, *addr = *addr + 1, old`.
+fn lower_compound_assign(node: &mut Node, ctx: &Context) -> Result<(), SemaError> {
+    let loc = node.loc;
+    let (op, mut target, rhs, addr, old) = match std::mem::replace(&mut node.value, NodeKind::Break)
+    {
+        NodeKind::CompoundAssign {
+            op,
+            lhs,
+            rhs,
+            addr,
+            old,
+        } => (op, lhs, rhs, addr, old),
+        _ => unreachable!(),
+    };
+    add_type(&mut target, ctx)?;
+    if !is_lvalue(&target) || matches!(target.ty, Some(Type::Array(..))) {
+        return Err(SemaError::not_lvalue(target.loc));
+    }
+    if is_const_lvalue(&target) {
+        return Err(SemaError::read_only(target.loc));
+    }
+    let ty = target.ty.clone().unwrap();
+    let addr = LocalVariable {
+        ty: Type::pointer_to(ty.clone()),
+        ..addr
+    };
+    let var = |var: &LocalVariable| Node::new(NodeKind::LocalVariable(var.clone()), loc);
+    let uniop = |kind: UniOpKind, expr: Node| {
+        let op = UniOp::new(kind, loc);
+        let expr = Box::new(expr);
+        Node::new(NodeKind::UniOp { op, expr }, loc)
+    };
+    let deref = || uniop(UniOpKind::Deref, var(&addr));
+    let assign = |lhs: Node, rhs: Node| {
+        let (lhs, rhs) = (Box::new(lhs), Box::new(rhs));
+        Node::new(NodeKind::Assign { lhs, rhs }, loc)
+    };
+    let comma = |lhs: Node, rhs: Node| {
+        let (lhs, rhs) = (Box::new(lhs), Box::new(rhs));
+        Node::new(NodeKind::Comma { lhs, rhs }, loc)
+    };
+    let lhs = Box::new(deref());
+    let update = assign(deref(), Node::new(NodeKind::BinOp { op, lhs, rhs }, loc));
+    let update = match old {
+        Some(old) => {
+            let old = LocalVariable { ty, ..old };
+            comma(assign(var(&old), deref()), comma(update, var(&old)))
+        }
+        None => update,
+    };
+    *node = comma(assign(var(&addr), uniop(UniOpKind::Addr, *target)), update);
+    add_type(node, ctx)
+}
+
+// Computes the type of `lhs op rhs`, rejecting operands that do not make sense for `op`.
+fn binop_type(op: &BinOp, lhs: &Type, rhs: &Type) -> Result<Type, SemaError> {
+    let invalid = || SemaError::invalid_operands(lhs.clone(), rhs.clone(), op.loc);
+    if matches!(lhs, Type::Struct(_)) || matches!(rhs, Type::Struct(_)) {
+        return Err(invalid());
+    }
+    if let BinOpKind::LogAnd | BinOpKind::LogOr = op.value {
+        return Ok(Type::Int);
+    }
+    if is_mixed_flonum(lhs, rhs) {
+        return Err(invalid());
+    }
+    // Numeric operands have been converted to a common type.
+    match op.value {
+        // An array operand decays to a pointer to its first element.
+        BinOpKind::Add => match (lhs.base(), rhs.base()) {
+            (None, None) => Ok(lhs.clone()),
+            (Some(base), None) | (None, Some(base)) => Ok(Type::pointer_to(base.clone())),
+            (Some(_), Some(_)) => Err(invalid()),
+        },
+        BinOpKind::Sub => match (lhs.base(), rhs.base()) {
+            (None, None) => Ok(lhs.clone()),
+            (Some(base), None) => Ok(Type::pointer_to(base.clone())),
+            (Some(l), Some(r)) if l.unqualified() == r.unqualified() => Ok(Type::Long),
+            _ => Err(invalid()),
+        },
+        BinOpKind::Mul | BinOpKind::Quo if lhs.is_numeric() && rhs.is_numeric() => Ok(lhs.clone()),
+        BinOpKind::Mul | BinOpKind::Quo => Err(invalid()),
+        BinOpKind::Rem
+        | BinOpKind::BitAnd
+        | BinOpKind::BitOr
+        | BinOpKind::BitXor
+        | BinOpKind::Shl
+        | BinOpKind::Shr
+            if lhs.is_integer() && rhs.is_integer() =>
+        {
+            Ok(lhs.clone())
+        }
+        BinOpKind::Rem
+        | BinOpKind::BitAnd
+        | BinOpKind::BitOr
+        | BinOpKind::BitXor
+        | BinOpKind::Shl
+        | BinOpKind::Shr => Err(invalid()),
+        BinOpKind::EQ | BinOpKind::NEQ | BinOpKind::LSS | BinOpKind::LEQ => Ok(Type::Int),
+        BinOpKind::LogAnd | BinOpKind::LogOr => unreachable!(),
+    }
+}
+
+// Tells whether one of the types is floating and the other is not a number,
+// such as a pointer, which C does not convert between.
+fn is_mixed_flonum(lhs: &Type, rhs: &Type) -> bool {
+    (lhs.is_flonum() && !rhs.is_numeric()) || (rhs.is_flonum() && !lhs.is_numeric())
+}
+
+// The common type that the operands of an arithmetic operator are converted
+// to. A floating operand makes the other one floating too. Integer types
+// narrower than int are promoted to int first; then the wider type wins,
+// and of two types of the same width the unsigned one.
+fn usual_arith_conv(lhs: &Type, rhs: &Type) -> Type {
+    if *lhs == Type::Double || *rhs == Type::Double {
+        return Type::Double;
+    }
+    if *lhs == Type::Float || *rhs == Type::Float {
+        return Type::Float;
+    }
+    let (lhs, rhs) = (int_promote(lhs), int_promote(rhs));
+    if lhs.size() != rhs.size() {
+        if lhs.size() > rhs.size() {
+            lhs
+        } else {
+            rhs
+        }
+    } else if rhs.is_unsigned() {
+        rhs
+    } else {
+        lhs
+    }
+}
+
+// Promotes an integer type narrower than int to int.
+fn int_promote(ty: &Type) -> Type {
+    if ty.size() < 4 {
+        Type::Int
+    } else {
+        ty.clone()
+    }
+}
+
+// Checks that `node` can be tested for zero, as a condition.
+fn check_scalar(node: &Node) -> Result<(), SemaError> {
+    let ty = node.ty.as_ref().unwrap();
+    if ty.is_scalar() {
+        Ok(())
+    } else {
+        Err(SemaError::invalid_operand(ty.clone(), node.loc))
+    }
+}
+
+// Wraps `node` in a conversion to `ty`, unless it already has that type.
+fn cast(node: &mut Node, ty: &Type) {
+    if node.ty.as_ref() == Some(ty) {
+        return;
+    }
+    let loc = node.loc;
+    // `Break` is only a placeholder while the node is moved into the cast.
+    let expr = std::mem::replace(node, Node::new(NodeKind::Break, loc));
+    *node = Node::new(NodeKind::Cast(Box::new(expr)), loc);
+    node.ty = Some(ty.clone());
+}
+
+// Converts `n` to the integer type `ty`, as the generated code does.
+fn convert(n: i64, ty: &Type) -> i64 {
+    match (ty.size(), ty.is_unsigned()) {
+        (1, false) => n as i8 as i64,
+        (1, true) => n as u8 as i64,
+        (2, false) => n as i16 as i64,
+        (2, true) => n as u16 as i64,
+        (4, false) => n as i32 as i64,
+        (4, true) => n as u32 as i64,
+        _ => n,
+    }
+}
+
+fn is_lvalue(node: &Node) -> bool {
+    match &node.value {
+        NodeKind::LocalVariable(_) | NodeKind::GlobalVariable(_) => true,
+        NodeKind::Member { expr, .. } => is_lvalue(expr),
+        NodeKind::UniOp { op, .. } => op.value == UniOpKind::Deref,
+        _ => false,
+    }
+}
+
+// Tells whether the lvalue `node`, whose type has been resolved, designates
+// a const object or a member of one.
+fn is_const_lvalue(node: &Node) -> bool {
+    match &node.value {
+        NodeKind::LocalVariable(var) => var.ty.is_const(),
+        NodeKind::GlobalVariable(var) => var.ty.is_const(),
+        NodeKind::Member { expr, name, .. } => {
+            let member = expr.ty.as_ref().unwrap().member(name).unwrap();
+            member.ty.is_const() || is_const_lvalue(expr)
+        }
+        NodeKind::UniOp { expr, .. } => expr.ty.as_ref().unwrap().base().unwrap().is_const(),
+        _ => false,
+    }
+}
+
+// Writes the value of `init` for an object of type `ty` at `offset` in `data`.
+fn write_init(
+    ty: &Type,
+    init: Initializer,
+    offset: usize,
+    data: &mut StaticData,
+    ctx: &Context,
+) -> Result<(), SemaError> {
+    let ty = ty.unqualified();
+    match (ty, init) {
+        (Type::Array(base, len), Initializer::List(items, loc)) => {
+            if items.len() > *len {
+                return Err(SemaError::invalid_initializer(ty.clone(), loc));
+            }
+            for (i, item) in items.into_iter().enumerate() {
+                write_init(base, item, offset + i * base.size() as usize, data, ctx)?;
+            }
+        }
+        // A char array may be initialized by a string literal, NUL included if it fits.
+        (
+            Type::Array(base, len),
+            Initializer::Expr(Node {
+                value: NodeKind::Str(bytes),
+                loc,
+                ..
+            }),
+        ) if matches!(**base, Type::Char | Type::UChar) => {
+            if bytes.len() > *len {
+                return Err(SemaError::invalid_initializer(ty.clone(), loc));
+            }
+            data.bytes[offset..offset + bytes.len()].copy_from_slice(&bytes);
+        }
+        // The members of a struct are initialized in order; a union initializes its first.
+        (Type::Struct(st), Initializer::List(items, loc)) => {
+            let members = st.members();
+            let limit = match st.kind {
+                StructKind::Struct => members.len(),
+                StructKind::Union => 1,
+            };
+            if items.len() > limit {
+                return Err(SemaError::invalid_initializer(ty.clone(), loc));
+            }
+            for (member, item) in members.iter().zip(items) {
+                write_init(&member.ty, item, offset + member.offset as usize, data, ctx)?;
+            }
+        }
+        (Type::Array(..), Initializer::Expr(node)) | (Type::Struct(_), Initializer::Expr(node)) => {
+            return Err(SemaError::invalid_initializer(ty.clone(), node.loc));
+        }
+        (_, Initializer::List(_, loc)) => {
+            return Err(SemaError::invalid_initializer(ty.clone(), loc));
+        }
+        (_, Initializer::Expr(mut node)) => {
+            add_type(&mut node, ctx)?;
+            let node_ty = node.ty.clone().unwrap();
+            if is_mixed_flonum(ty, &node_ty) {
+                return Err(SemaError::invalid_initializer(ty.clone(), node.loc));
+            }
+            if ty.is_numeric() && node_ty.is_numeric() {
+                cast(&mut node, ty);
+            }
+            if ty.is_flonum() {
+                let f = eval_double(&node)?;
+                let bytes = match ty {
+                    Type::Float => (f as f32).to_le_bytes().to_vec(),
+                    _ => f.to_le_bytes().to_vec(),
+                };
+                data.bytes[offset..offset + bytes.len()].copy_from_slice(&bytes);
+                return Ok(());
+            }
+            let (target, value) = eval(&node)?;
+            match target {
+                Some(target) if ty.base().is_some() => data.relocs.push(Reloc {
+                    offset,
+                    target,
+                    addend: value,
+                }),
+                Some(_) => return Err(SemaError::not_constant(node.loc)),
+                None => {
+                    let size = ty.size() as usize;
+                    let bytes = value.to_le_bytes();
+                    data.bytes[offset..offset + size].copy_from_slice(&bytes[..size]);
+                }
+            }
+        }
+        (_, Initializer::Data(_)) => unreachable!(),
+    }
+    Ok(())
+}
+
+// Evaluates a constant expression to an optional address it is relative to
+// and an offset from that address.
+fn eval(node: &Node) -> Result<(Option<RelocTarget>, i64), SemaError> {
+    match &node.value {
+        NodeKind::Int(n) => Ok((None, *n)),
+        // A floating value is truncated toward zero.
+        NodeKind::Cast(expr) if expr.ty.as_ref().unwrap().is_flonum() => {
+            let f = eval_double(expr)?;
+            let ty = node.ty.as_ref().unwrap();
+            let n = if ty.is_unsigned() {
+                f as u64 as i64
+            } else {
+                f as i64
+            };
+            Ok((None, convert(n, ty)))
+        }
+        NodeKind::Cast(expr) => match eval(expr)? {
+            (None, n) => Ok((None, convert(n, node.ty.as_ref().unwrap()))),
+            // Only a pointer-sized integer can hold an address.
+            (target, n) if node.ty.as_ref().unwrap().size() == 8 => Ok((target, n)),
+            _ => Err(SemaError::not_constant(node.loc)),
+        },
+        // Arrays decay to the address of their first element.
+        NodeKind::Str(bytes) => Ok((Some(RelocTarget::Str(bytes.clone())), 0)),
+        NodeKind::GlobalVariable(var) if matches!(var.ty, Type::Array(..)) => {
+            Ok((Some(RelocTarget::Global(var.name.clone())), 0))
+        }
+        NodeKind::UniOp { op, expr } => match op.value {
+            UniOpKind::Minus => match eval(expr)? {
+                (None, n) => Ok((None, convert(n.wrapping_neg(), node.ty.as_ref().unwrap()))),
+                _ => Err(SemaError::not_constant(node.loc)),
+            },
+            UniOpKind::Addr => eval_addr(expr),
+            UniOpKind::Deref => Err(SemaError::not_constant(node.loc)),
+            UniOpKind::Not => Ok((None, !eval_bool(expr)? as i64)),
+            UniOpKind::BitNot => match eval(expr)? {
+                (None, n) => Ok((None, convert(!n, node.ty.as_ref().unwrap()))),
+                _ => Err(SemaError::not_constant(node.loc)),
+            },
+        },
+        // The right operand is evaluated only if it decides the result, as at run time.
+        NodeKind::BinOp { op, lhs, rhs } if op.value == BinOpKind::LogAnd => {
+            Ok((None, (eval_bool(lhs)? && eval_bool(rhs)?) as i64))
+        }
+        NodeKind::BinOp { op, lhs, rhs } if op.value == BinOpKind::LogOr => {
+            Ok((None, (eval_bool(lhs)? || eval_bool(rhs)?) as i64))
+        }
+        NodeKind::Conditional { cond, then, els } => {
+            if eval_bool(cond)? {
+                eval(then)
+            } else {
+                eval(els)
+            }
+        }
+        // A comparison of floating operands.
+        NodeKind::BinOp { op, lhs, rhs } if lhs.ty.as_ref().unwrap().is_flonum() => {
+            let (l, r) = (eval_double(lhs)?, eval_double(rhs)?);
+            let b = match op.value {
+                BinOpKind::EQ => l == r,
+                BinOpKind::NEQ => l != r,
+                BinOpKind::LSS => l < r,
+                BinOpKind::LEQ => l <= r,
+                _ => return Err(SemaError::not_constant(node.loc)),
+            };
+            Ok((None, b as i64))
+        }
+        NodeKind::BinOp { op, lhs, rhs } => {
+            let lhs_ty = lhs.ty.as_ref().unwrap();
+            let rhs_ty = rhs.ty.as_ref().unwrap();
+            let ty = node.ty.as_ref().unwrap();
+            let scale = |ty: &Type| ty.base().map_or(1, |base| base.size() as i64);
+            // Operands are normalized to their type, so unsigned ones compare as u64.
+            let unsigned = lhs_ty.is_unsigned();
+            let (target, n) = match (&op.value, eval(lhs)?, eval(rhs)?) {
+                (BinOpKind::Add, (target, l), (None, r)) => {
+                    (target, l.wrapping_add(r.wrapping_mul(scale(lhs_ty))))
+                }
+                (BinOpKind::Add, (None, l), (target, r)) => {
+                    (target, l.wrapping_mul(scale(rhs_ty)).wrapping_add(r))
+                }
+                (BinOpKind::Sub, (target, l), (None, r)) => {
+                    (target, l.wrapping_sub(r.wrapping_mul(scale(lhs_ty))))
+                }
+                (BinOpKind::Mul, (None, l), (None, r)) => (None, l.wrapping_mul(r)),
+                (BinOpKind::Quo, (None, _), (None, 0)) => {
+                    return Err(SemaError::not_constant(node.loc))
+                }
+                (BinOpKind::Quo, (None, l), (None, r)) if unsigned => {
+                    (None, ((l as u64) / (r as u64)) as i64)
+                }
+                (BinOpKind::Quo, (None, l), (None, r)) => (None, l.wrapping_div(r)),
+                (BinOpKind::Rem, (None, _), (None, 0)) => {
+                    return Err(SemaError::not_constant(node.loc))
+                }
+                (BinOpKind::Rem, (None, l), (None, r)) if unsigned => {
+                    (None, ((l as u64) % (r as u64)) as i64)
+                }
+                (BinOpKind::Rem, (None, l), (None, r)) => (None, l.wrapping_rem(r)),
+                (BinOpKind::BitAnd, (None, l), (None, r)) => (None, l & r),
+                (BinOpKind::BitOr, (None, l), (None, r)) => (None, l | r),
+                (BinOpKind::BitXor, (None, l), (None, r)) => (None, l ^ r),
+                (BinOpKind::Shl, (None, l), (None, r)) => (None, l.wrapping_shl(r as u32)),
+                (BinOpKind::Shr, (None, l), (None, r)) if unsigned => {
+                    (None, (l as u64).wrapping_shr(r as u32) as i64)
+                }
+                (BinOpKind::Shr, (None, l), (None, r)) => (None, l.wrapping_shr(r as u32)),
+                (BinOpKind::EQ, (None, l), (None, r)) => (None, (l == r) as i64),
+                (BinOpKind::NEQ, (None, l), (None, r)) => (None, (l != r) as i64),
+                (BinOpKind::LSS, (None, l), (None, r)) if unsigned => {
+                    (None, ((l as u64) < (r as u64)) as i64)
+                }
+                (BinOpKind::LSS, (None, l), (None, r)) => (None, (l < r) as i64),
+                (BinOpKind::LEQ, (None, l), (None, r)) if unsigned => {
+                    (None, ((l as u64) <= (r as u64)) as i64)
+                }
+                (BinOpKind::LEQ, (None, l), (None, r)) => (None, (l <= r) as i64),
+                _ => return Err(SemaError::not_constant(node.loc)),
+            };
+            match target {
+                None if ty.is_integer() => Ok((None, convert(n, ty))),
+                _ => Ok((target, n)),
+            }
+        }
+        _ => Err(SemaError::not_constant(node.loc)),
+    }
+}
+
+// Evaluates a constant expression of a floating type, or of an integer type
+// whose value is then converted to a double.
+fn eval_double(node: &Node) -> Result<f64, SemaError> {
+    let ty = node.ty.as_ref().unwrap();
+    if ty.is_integer() {
+        return match eval(node)? {
+            (None, n) if ty.is_unsigned() => Ok(n as u64 as f64),
+            (None, n) => Ok(n as f64),
+            _ => Err(SemaError::not_constant(node.loc)),
+        };
+    }
+    let f = match &node.value {
+        NodeKind::Float(f) => *f,
+        NodeKind::Cast(expr) => eval_double(expr)?,
+        NodeKind::Conditional { cond, then, els } => {
+            if eval_bool(cond)? {
+                eval_double(then)?
+            } else {
+                eval_double(els)?
+            }
+        }
+        NodeKind::UniOp { op, expr } if op.value == UniOpKind::Minus => -eval_double(expr)?,
+        NodeKind::BinOp { op, lhs, rhs } => {
+            let (l, r) = (eval_double(lhs)?, eval_double(rhs)?);
+            match op.value {
+                BinOpKind::Add => l + r,
+                BinOpKind::Sub => l - r,
+                BinOpKind::Mul => l * r,
+                BinOpKind::Quo => l / r,
+                _ => return Err(SemaError::not_constant(node.loc)),
+            }
+        }
+        _ => return Err(SemaError::not_constant(node.loc)),
+    };
+    // A float is rounded to single precision at every step, as at run time.
+    Ok(if *ty == Type::Float {
+        f as f32 as f64
+    } else {
+        f
+    })
+}
+
+// Evaluates whether a constant expression is unequal to zero. An address
+// is never null.
+fn eval_bool(node: &Node) -> Result<bool, SemaError> {
+    if node.ty.as_ref().unwrap().is_flonum() {
+        return Ok(eval_double(node)? != 0.0);
+    }
+    match eval(node)? {
+        (None, n) => Ok(n != 0),
+        (Some(_), _) => Ok(true),
+    }
+}
+
+// Evaluates the address of the lvalue `node`.
+fn eval_addr(node: &Node) -> Result<(Option<RelocTarget>, i64), SemaError> {
+    match &node.value {
+        NodeKind::GlobalVariable(var) => Ok((Some(RelocTarget::Global(var.name.clone())), 0)),
+        NodeKind::UniOp { op, expr } if op.value == UniOpKind::Deref => eval(expr),
+        _ => Err(SemaError::not_constant(node.loc)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn analyze_str(input: &str) -> Result<Program, SemaError> {
+        let mut program = parse(lex(input).unwrap()).unwrap();
+        analyze(&mut program)?;
+        Ok(program)
+    }
+
+    // The operand of an implicit conversion inserted around `node`, if any.
+    fn uncast(node: &Node) -> &Node {
+        match &node.value {
+            NodeKind::Cast(expr) => expr,
+            _ => node,
+        }
+    }
+
+    #[test]
+    fn test_types_are_attached() -> Result<(), SemaError> {
+        let program = analyze_str("int main() { int a; return a = 1 + 2; }")?;
+        let stmts = match &program.functions[0].body.value {
+            NodeKind::Block(stmts) => stmts,
+            _ => panic!(),
+        };
+        assert_eq!(stmts[1].ty, None);
+        match &stmts[1].value {
+            NodeKind::Return(node) => {
+                assert_eq!(node.ty, Some(Type::Int));
+                match &node.value {
+                    NodeKind::Assign { lhs, rhs } => {
+                        assert_eq!(lhs.ty, Some(Type::Int));
+                        assert_eq!(rhs.ty, Some(Type::Int));
+                    }
+                    _ => panic!(),
+                }
+            }
+            _ => panic!(),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_pointer_types() -> Result<(), SemaError> {
+        let program = analyze_str("int main() { int x; int *p; return *(p + 1) + (&x - p); }")?;
+        let stmts = match &program.functions[0].body.value {
+            NodeKind::Block(stmts) => stmts,
+            _ => panic!(),
+        };
+        let (lhs, rhs) = match &stmts[2].value {
+            NodeKind::Return(node) => match &uncast(node).value {
+                NodeKind::BinOp { lhs, rhs, .. } => (lhs, rhs),
+                _ => panic!(),
+            },
+            _ => panic!(),
+        };
+        // The int operand is converted to long, the type of a pointer difference.
+        assert_eq!(lhs.ty, Some(Type::Long));
+        assert_eq!(uncast(lhs).ty, Some(Type::Int));
+        match &uncast(lhs).value {
+            NodeKind::UniOp { expr, .. } => {
+                assert_eq!(expr.ty, Some(Type::pointer_to(Type::Int)))
+            }
+            _ => panic!(),
+        }
+        assert_eq!(rhs.ty, Some(Type::Long));
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_pointer_operations() {
+        match analyze_str("int main() { int x; return *x; }") {
+            Err(SemaError {
+                value: SemaErrorKind::NotPointer(Type::Int),
+                ..
+            }) => (),
+            _ => panic!(),
+        }
+        match analyze_str("int main() { int *p; return p + p; }") {
+            Err(SemaError {
+                value: SemaErrorKind::InvalidOperands(_, _),
+                ..
+            }) => (),
+            _ => panic!(),
+        }
+        match analyze_str("int main() { return &1; }") {
+            Err(SemaError {
+                value: SemaErrorKind::NotLvalue,
+                ..
+            }) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn test_sizeof() -> Result<(), SemaError> {
+        let program = analyze_str(
+            "int main() { int a[3][4]; return sizeof a + sizeof(a[1]) + sizeof a[1][2]; }",
+        )?;
+        let stmts = match &program.functions[0].body.value {
+            NodeKind::Block(stmts) => stmts,
+            _ => panic!(),
+        };
+        let sizes = match &stmts[1].value {
+            NodeKind::Return(node) => match &uncast(node).value {
+                NodeKind::BinOp { lhs, rhs, .. } => match &lhs.value {
+                    NodeKind::BinOp { lhs: a, rhs: b, .. } => {
+                        vec![&a.value, &b.value, &rhs.value]
+                    }
+                    _ => panic!(),
+                },
+                _ => panic!(),
+            },
+            _ => panic!(),
+        };
+        assert_eq!(
+            sizes,
+            vec![&NodeKind::Int(48), &NodeKind::Int(16), &NodeKind::Int(4)]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_array_decay() -> Result<(), SemaError> {
+        let program = analyze_str("int main() { int a[3]; return *(a + 1); }")?;
+        let stmts = match &program.functions[0].body.value {
+            NodeKind::Block(stmts) => stmts,
+            _ => panic!(),
+        };
+        match &stmts[1].value {
+            NodeKind::Return(node) => match &node.value {
+                NodeKind::UniOp { expr, .. } => {
+                    assert_eq!(expr.ty, Some(Type::pointer_to(Type::Int)))
+                }
+                _ => panic!(),
+            },
+            _ => panic!(),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_string_literal() -> Result<(), SemaError> {
+        let program = analyze_str("int main() { char *s = \"abc\"; return s[0]; }")?;
+        let stmts = match &program.functions[0].body.value {
+            NodeKind::Block(stmts) => stmts,
+            _ => panic!(),
+        };
+        let init = match &stmts[0].value {
+            NodeKind::Block(inits) => &inits[0],
+            _ => panic!(),
+        };
+        match &init.value {
+            NodeKind::Assign { lhs, rhs } => {
+                assert_eq!(lhs.ty, Some(Type::pointer_to(Type::Char)));
+                assert_eq!(rhs.ty, Some(Type::array_of(Type::Char, 4)));
+            }
+            _ => panic!(),
+        }
+        Ok(())
+    }
+
+    fn static_data(program: &Program, i: usize) -> &StaticData {
+        match &program.globals[i].init {
+            Some(Initializer::Data(data)) => data,
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn test_global_initializers() -> Result<(), SemaError> {
+        let program = analyze_str(
+            "int a = 258; char b[4] = \"ab\"; int c[3] = {1, -2 * 3,}; int *d = &c[1] + 1; char *e = \"x\";",
+        )?;
+        assert_eq!(static_data(&program, 0).bytes, vec![2, 1, 0, 0]);
+        assert_eq!(static_data(&program, 1).bytes, vec![97, 98, 0, 0]);
+        assert_eq!(
+            static_data(&program, 2).bytes,
+            vec![1, 0, 0, 0, 250, 255, 255, 255, 0, 0, 0, 0]
+        );
+        assert_eq!(
+            static_data(&program, 3).relocs,
+            vec![Reloc {
+                offset: 0,
+                target: RelocTarget::Global("c".to_string()),
+                addend: 8,
+            }]
+        );
+        assert_eq!(
+            static_data(&program, 4).relocs[0].target,
+            RelocTarget::Str(b"x".to_vec())
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_global_initializers() {
+        for input in &[
+            "int a; int b = a;",
+            "int a; int b = &a;",
+            "int f() { return 0; } int x = f();",
+        ] {
+            match analyze_str(input) {
+                Err(SemaError {
+                    value: SemaErrorKind::NotConstant,
+                    ..
+                }) => (),
+                r => panic!("{}: {:?}", input, r),
+            }
+        }
+        for input in &[
+            "int a[2] = {1, 2, 3};",
+            "int a = {1};",
+            "char s[2] = \"abc\";",
+        ] {
+            match analyze_str(input) {
+                Err(SemaError {
+                    value: SemaErrorKind::InvalidInitializer(_),
+                    ..
+                }) => (),
+                r => panic!("{}: {:?}", input, r),
+            }
+        }
+    }
+
+    #[test]
+    fn test_members() -> Result<(), SemaError> {
+        let program = analyze_str(
+            "int main() { struct { char a; int *b; } x, *p = &x; return *p->b + x.a; }",
+        )?;
+        let stmts = match &program.functions[0].body.value {
+            NodeKind::Block(stmts) => stmts,
+            _ => panic!(),
+        };
+        let (lhs, rhs) = match &stmts[1].value {
+            NodeKind::Return(node) => match &node.value {
+                NodeKind::BinOp { lhs, rhs, .. } => (lhs, rhs),
+                _ => panic!(),
+            },
+            _ => panic!(),
+        };
+        match &lhs.value {
+            NodeKind::UniOp { expr, .. } => match &expr.value {
+                NodeKind::Member { offset, .. } => {
+                    assert_eq!(*offset, 8);
+                    assert_eq!(expr.ty, Some(Type::pointer_to(Type::Int)));
+                }
+                _ => panic!(),
+            },
+            _ => panic!(),
+        }
+        assert_eq!(rhs.ty, Some(Type::Int));
+        assert_eq!(uncast(rhs).ty, Some(Type::Char));
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_members() {
+        match analyze_str("int main() { int x; return x.a; }") {
+            Err(SemaError {
+                value: SemaErrorKind::NotStruct(Type::Int),
+                ..
+            }) => (),
+            _ => panic!(),
+        }
+        match analyze_str("int main() { struct { int a; } x; return x.b; }") {
+            Err(SemaError {
+                value: SemaErrorKind::NoSuchMember(name),
+                ..
+            }) => assert_eq!(name, "b"),
+            _ => panic!(),
+        }
+        match analyze_str(
+            "int main() { struct { int a; } x; struct { int a; } y; x = y; return 0; }",
+        ) {
+            Err(SemaError {
+                value: SemaErrorKind::InvalidOperands(..),
+                ..
+            }) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn test_usual_arith_conv() {
+        let cases = vec![
+            (Type::Char, Type::Short, Type::Int),
+            (Type::UChar, Type::Int, Type::Int),
+            (Type::Int, Type::UInt, Type::UInt),
+            (Type::UInt, Type::Long, Type::Long),
+            (Type::Long, Type::ULong, Type::ULong),
+            (Type::ULong, Type::Char, Type::ULong),
+            (Type::Float, Type::ULong, Type::Float),
+            (Type::Float, Type::Double, Type::Double),
+            (Type::Char, Type::Double, Type::Double),
+        ];
+        for (lhs, rhs, ty) in cases {
+            assert_eq!(usual_arith_conv(&lhs, &rhs), ty);
+            assert_eq!(usual_arith_conv(&rhs, &lhs), ty);
+        }
+    }
+
+    #[test]
+    fn test_integer_constants() -> Result<(), SemaError> {
+        let program = analyze_str(
+            "int a = -1 < 1u; char b = 300; unsigned char c = -1; long d = 0xffffffffu + 1; int e = 4294967295u / 2;",
+        )?;
+        let bytes: Vec<_> = (0..5)
+            .map(|i| static_data(&program, i).bytes.clone())
+            .collect();
+        assert_eq!(bytes[0], vec![0, 0, 0, 0]);
+        assert_eq!(bytes[1], vec![44]);
+        assert_eq!(bytes[2], vec![255]);
+        assert_eq!(bytes[3], vec![0; 8]);
+        assert_eq!(bytes[4], vec![255, 255, 255, 127]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_float_conversions() -> Result<(), SemaError> {
+        let program = analyze_str(
+            "double sqrt(double); int f(float x) { return x; } \
+             int main() { double d; d = 1 + sqrt(2); return f(d) + printf(\"%f\", 1.5f); }",
+        )?;
+        let ret = match &program.functions[0].body.value {
+            NodeKind::Block(stmts) => match &stmts[0].value {
+                NodeKind::Return(expr) => expr,
+                _ => panic!(),
+            },
+            _ => panic!(),
+        };
+        assert_eq!(ret.ty, Some(Type::Int));
+        assert_eq!(uncast(ret).ty, Some(Type::Float));
+        let stmts = match &program.functions[1].body.value {
+            NodeKind::Block(stmts) => stmts,
+            _ => panic!(),
+        };
+        let rhs = match &stmts[1].value {
+            NodeKind::Assign { rhs, .. } => rhs,
+            _ => panic!(),
+        };
+        let (lhs, call) = match &rhs.value {
+            NodeKind::BinOp { lhs, rhs, .. } => (lhs, rhs),
+            _ => panic!(),
+        };
+        assert_eq!(rhs.ty, Some(Type::Double));
+        assert_eq!(lhs.ty, Some(Type::Double));
+        assert_eq!(uncast(lhs).ty, Some(Type::Int));
+        match &call.value {
+            NodeKind::FunCall { args, .. } => {
+                assert_eq!(args[0].ty, Some(Type::Double));
+                assert_eq!(uncast(&args[0]).ty, Some(Type::Int));
+            }
+            _ => panic!(),
+        }
+        let (f, printf) = match &stmts[2].value {
+            NodeKind::Return(expr) => match &expr.value {
+                NodeKind::BinOp { lhs, rhs, .. } => (lhs, rhs),
+                _ => panic!(),
+            },
+            _ => panic!(),
+        };
+        // A prototype converts the argument; otherwise a float becomes a double.
+        match (&f.value, &printf.value) {
+            (NodeKind::FunCall { args: f, .. }, NodeKind::FunCall { args: printf, .. }) => {
+                assert_eq!(f[0].ty, Some(Type::Float));
+                assert_eq!(printf[1].ty, Some(Type::Double));
+            }
+            _ => panic!(),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_float_constants() -> Result<(), SemaError> {
+        let program = analyze_str(
+            "double a = 1.5; float b = -2; int c = 2.9 * 2; unsigned long d = 1e19; int e = 0.1 + 0.2 == 0.3; double f = 18446744073709551615u;",
+        )?;
+        let bytes: Vec<_> = (0..6)
+            .map(|i| static_data(&program, i).bytes.clone())
+            .collect();
+        assert_eq!(bytes[0], 1.5f64.to_le_bytes().to_vec());
+        assert_eq!(bytes[1], (-2.0f32).to_le_bytes().to_vec());
+        assert_eq!(bytes[2], vec![5, 0, 0, 0]);
+        assert_eq!(
+            bytes[3],
+            10_000_000_000_000_000_000u64.to_le_bytes().to_vec()
+        );
+        assert_eq!(bytes[4], vec![0, 0, 0, 0]);
+        assert_eq!(bytes[5], 18446744073709551616f64.to_le_bytes().to_vec());
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_float_operands() {
+        for input in &[
+            "int main() { int *p; double d; p + d; }",
+            "int main() { int *p; p = 1.5; }",
+            "int main() { int *p; return p == 0.0; }",
+            "int *f() { return 1.0; }",
+            "double f(double); int main() { int *p; f(p); }",
+        ] {
+            match analyze_str(input) {
+                Err(SemaError {
+                    value: SemaErrorKind::InvalidOperands(..),
+                    ..
+                }) => (),
+                r => panic!("{}: {:?}", input, r),
+            }
+        }
+    }
+
+    #[test]
+    fn test_operator_types() -> Result<(), SemaError> {
+        let program = analyze_str(
+            "int main() { char c; unsigned u; long l; int *p; \
+             c << l; u >> c; ~c; !p; p && 1.5; c ? u : l; c ? \"a\" : \"bc\"; c, p; }",
+        )?;
+        let stmts = match &program.functions[0].body.value {
+            NodeKind::Block(stmts) => stmts,
+            _ => panic!(),
+        };
+        let types: Vec<_> = stmts[4..]
+            .iter()
+            .map(|stmt| stmt.ty.clone().unwrap())
+            .collect();
+        let int_ptr = Type::pointer_to(Type::Int);
+        assert_eq!(
+            types,
+            vec![
+                Type::Int,
+                Type::UInt,
+                Type::Int,
+                Type::Int,
+                Type::Int,
+                Type::Long,
+                Type::pointer_to(Type::Char),
+                int_ptr,
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_operators() {
+        let char_ptr = "int main() { char *p; int *q; double d; struct { int a; } s; ";
+        for input in &[
+            "p % 2;",
+            "d & 1;",
+            "1 << d;",
+            "p | 1;",
+            "p ? 1 : q;",
+            "1 ? s : 2;",
+        ] {
+            let input = format!("{}{} }}", char_ptr, input);
+            match analyze_str(&input) {
+                Err(SemaError {
+                    value: SemaErrorKind::InvalidOperands(..),
+                    ..
+                }) => (),
+                r => panic!("{}: {:?}", input, r),
+            }
+        }
+        for input in &["~p;", "~d;", "!s;", "s && 1;", "s ? 1 : 2;"] {
+            let input = format!("{}{} }}", char_ptr, input);
+            match analyze_str(&input) {
+                Err(SemaError {
+                    value: SemaErrorKind::InvalidOperand(_),
+                    ..
+                }) => (),
+                r => panic!("{}: {:?}", input, r),
+            }
+        }
+    }
+
+    #[test]
+    fn test_operator_constants() -> Result<(), SemaError> {
+        let program = analyze_str(
+            "int a = -7 % 3; unsigned b = 0xffffffffu >> 28; int c = -16 >> 2; char d = 1 << 7; \
+             int e = ~0 & 0xf0 | 3 ^ 1; int f = 0 && 1 / 0; int g = 2.5 || 0; int h = 0 ? 1 : 2.5;",
+        )?;
+        let values: Vec<_> = (0..8)
+            .map(|i| {
+                let bytes = &static_data(&program, i).bytes;
+                let mut buf = [0; 4];
+                buf[..bytes.len()].copy_from_slice(bytes);
+                i32::from_le_bytes(buf)
+            })
+            .collect();
+        assert_eq!(values, vec![-1, 15, -4, 128, 242, 0, 1, 2]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_compound_assign() -> Result<(), SemaError> {
+        let program = analyze_str("int main() { char c; double d; int *p; c += 1.5; d++; --p; }")?;
+        let stmts = match &program.functions[0].body.value {
+            NodeKind::Block(stmts) => stmts,
+            _ => panic!(),
+        };
+        let types: Vec<_> = stmts[3..]
+            .iter()
+            .map(|stmt| stmt.ty.clone().unwrap())
+            .collect();
+        assert_eq!(
+            types,
+            vec![Type::Char, Type::Double, Type::pointer_to(Type::Int)]
+        );
+        // `c += 1.5` becomes `addr = &c, *addr = *addr + 1.5`.
+        let (save, update) = match &stmts[3].value {
+            NodeKind::Comma { lhs, rhs } => (lhs, rhs),
+            _ => panic!(),
+        };
+        let char_ptr = Type::pointer_to(Type::Char);
+        assert_eq!(uncast(save).ty, Some(char_ptr));
+        match &update.value {
+            NodeKind::Assign { rhs, .. } => {
+                assert_eq!(rhs.ty, Some(Type::Char));
+                assert_eq!(uncast(rhs).ty, Some(Type::Double));
+            }
+            _ => panic!(),
+        }
+        for input in &[
+            "int main() { 1 += 2; }",
+            "int main() { int a[2]; a++; }",
+            "int main() { int a; ++(a + 1); }",
+        ] {
+            match analyze_str(input) {
+                Err(SemaError {
+                    value: SemaErrorKind::NotLvalue,
+                    ..
+                }) => (),
+                r => panic!("{}: {:?}", input, r),
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_switch() -> Result<(), SemaError> {
+        let program = analyze_str(
+            "int main() { char c; switch (c) { case 1 + 2: case -1: case 1L << 32: break; } }",
+        )?;
+        let (cond, cases) = match &program.functions[0].body.value {
+            NodeKind::Block(stmts) => match &stmts[1].value {
+                NodeKind::Switch { cond, cases, .. } => (cond, cases),
+                _ => panic!(),
+            },
+            _ => panic!(),
+        };
+        assert_eq!(cond.ty, Some(Type::Int));
+        let values: Vec<_> = cases.iter().map(|case| case.value).collect();
+        assert_eq!(values, vec![3, -1, 0]);
+        match analyze_str("int main() { switch (1) { case 1: case 0: case 2 - 1: break; } }") {
+            Err(SemaError {
+                value: SemaErrorKind::DuplicateCase(1),
+                loc,
+            }) => assert_eq!(loc, Loc(49, 50)),
+            r => panic!("{:?}", r),
+        }
+        for (input, loc) in &[
+            (
+                "int main() { int x; switch (1) { case x: break; } }",
+                Loc(38, 39),
+            ),
+            (
+                "int main() { switch (1) { case 1.0: break; } }",
+                Loc(31, 34),
+            ),
+            (
+                "int main() { switch (1) { case \"a\": break; } }",
+                Loc(31, 34),
+            ),
+            (
+                "int main() { switch (1) { case 1 + f(): break; } }",
+                Loc(33, 34),
+            ),
+        ] {
+            match analyze_str(input) {
+                Err(SemaError {
+                    value: SemaErrorKind::NotConstant,
+                    loc: l,
+                }) => assert_eq!(l, *loc, "{}", input),
+                r => panic!("{}: {:?}", input, r),
+            }
+        }
+        match analyze_str("int main() { switch (1.0) { case 1: break; } }") {
+            Err(SemaError {
+                value: SemaErrorKind::InvalidOperand(Type::Double),
+                ..
+            }) => (),
+            r => panic!("{:?}", r),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_only() {
+        let cases = vec![
+            (
+                "int main() { const int x = 1; x = 2; return x; }",
+                Loc(30, 31),
+            ),
+            (
+                "int main() { const int x = 1; x *= 2; return x; }",
+                Loc(30, 31),
+            ),
+            (
+                "int main() { int a; const int *p = &a; *p = 1; return a; }",
+                Loc(39, 40),
+            ),
+            (
+                "int main() { struct { const int m; } s; s.m = 1; return 0; }",
+                Loc(42, 43),
+            ),
+            (
+                "int main() { const struct { int m; } s; s.m++; return 0; }",
+                Loc(42, 43),
+            ),
+        ];
+        for (input, expected) in cases {
+            match analyze_str(input) {
+                Err(SemaError {
+                    value: SemaErrorKind::ReadOnly,
+                    loc,
+                }) => assert_eq!(loc, expected, "{}", input),
+                r => panic!("{}: {:?}", input, r),
+            }
+        }
+        // A pointer to const may itself be reassigned.
+        let program = analyze_str("int main() { int a; const int *p = &a; p = 0; return *p; }");
+        assert!(program.is_ok());
+    }
+
+    #[test]
+    fn test_struct_by_value() {
+        let cases = vec![
+            ("struct S { int a; }; int f(struct S s) { return s.a; }", 25),
+            ("struct S { int a; }; struct S g(); int f(struct S *p);", 30),
+            (
+                "struct S { int a; }; int main() { struct S x; printf(\"%d\", x); return 0; }",
+                59,
+            ),
+            (
+                "struct S { int a; }; int main() { struct S x; return x; }",
+                53,
+            ),
+            (
+                "struct S { int a; }; int f(int); int main() { struct S x; return f(x); }",
+                67,
+            ),
+        ];
+        for (input, pos) in cases {
+            match analyze_str(input) {
+                Err(SemaError {
+                    value: SemaErrorKind::StructByValue(Type::Struct(_)),
+                    loc,
+                }) => assert_eq!(loc, Loc(pos, pos + 1), "{}", input),
+                r => panic!("{}: {:?}", input, r),
+            }
+        }
+    }
+
+    #[test]
+    fn test_minus() -> Result<(), SemaError> {
+        let program = analyze_str("int main() { char c; double d; -c; -d; return 0; }")?;
+        let stmts = match &program.functions[0].body.value {
+            NodeKind::Block(stmts) => stmts,
+            _ => panic!(),
+        };
+        let types: Vec<_> = stmts[2..4]
+            .iter()
+            .map(|stmt| match &stmt.value {
+                NodeKind::UniOp { expr, .. } => (stmt.ty.clone(), expr.ty.clone()),
+                _ => panic!(),
+            })
+            .collect();
+        // A char is promoted to int before it is negated.
+        assert_eq!(
+            types,
+            vec![
+                (Some(Type::Int), Some(Type::Int)),
+                (Some(Type::Double), Some(Type::Double))
+            ]
+        );
+        match analyze_str("int main() { int *p; return -p; }") {
+            Err(SemaError {
+                value: SemaErrorKind::InvalidOperand(Type::Ptr(_)),
+                loc,
+            }) => assert_eq!(loc, Loc(29, 30)),
+            r => panic!("{:?}", r),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_not_lvalue() {
+        match analyze_str("int main() { 1 = 2; return 0; }") {
+            Err(SemaError {
+                value: SemaErrorKind::NotLvalue,
+                loc,
+            }) => assert_eq!(loc, Loc(13, 14)),
+            _ => panic!(),
+        }
+    }
+}
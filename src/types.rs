@@ -0,0 +1,372 @@
+use std::cell::RefCell;
+use std::convert::TryFrom;
+use std::fmt;
+use std::rc::Rc;
+
+// The largest size of an object, and the largest offset into a struct or a
+// stack frame. It is small enough that sums and alignments of such sizes
+// still fit in the i32 that they are stored in.
+pub const MAX_SIZE: i32 = 1 << 30;
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Type {
+    Char,
+    Short,
+    Int,
+    Long,
+    UChar,
+    UShort,
+    UInt,
+    ULong,
+    Float,
+    Double,
+    Ptr(Box<Type>),
+    Array(Box<Type>, usize),
+    Struct(Rc<StructType>),
+    // A const-qualified type. Qualifiers only appear in declared types, and
+    // in the types that pointers and arrays derive from them: the type of
+    // an expression never has one at the top level.
+    Const(Box<Type>),
+}
+
+impl Type {
+    pub fn pointer_to(base: Type) -> Self {
+        Type::Ptr(Box::new(base))
+    }
+
+    pub fn array_of(base: Type, len: usize) -> Self {
+        Type::Array(Box::new(base), len)
+    }
+
+    // Like `array_of`, but fails if the array would be larger than `MAX_SIZE`.
+    pub fn checked_array_of(base: Type, len: usize) -> Option<Self> {
+        let size = i32::try_from(len).ok()?.checked_mul(base.size())?;
+        if size > MAX_SIZE {
+            return None;
+        }
+        Some(Type::array_of(base, len))
+    }
+
+    // Qualifies the type with const. Qualifying an array qualifies its
+    // elements.
+    pub fn const_of(ty: Type) -> Self {
+        match ty {
+            Type::Const(_) => ty,
+            Type::Array(base, len) => Type::array_of(Type::const_of(*base), len),
+            _ => Type::Const(Box::new(ty)),
+        }
+    }
+
+    pub fn is_const(&self) -> bool {
+        matches!(self, Type::Const(_))
+    }
+
+    // The type without its top-level qualifiers, as used for a value.
+    pub fn unqualified(&self) -> &Type {
+        match self {
+            Type::Const(ty) => ty,
+            _ => self,
+        }
+    }
+
+    pub fn size(&self) -> i32 {
+        match self {
+            Type::Char | Type::UChar => 1,
+            Type::Short | Type::UShort => 2,
+            Type::Int | Type::UInt | Type::Float => 4,
+            Type::Long | Type::ULong | Type::Double | Type::Ptr(_) => 8,
+            Type::Array(base, len) => i32::try_from(*len)
+                .ok()
+                .and_then(|len| base.size().checked_mul(len))
+                .expect("array sizes are checked by the parser"),
+            Type::Struct(st) => st.layout.borrow().as_ref().map_or(0, |l| l.size),
+            Type::Const(ty) => ty.size(),
+        }
+    }
+
+    pub fn align(&self) -> i32 {
+        match self {
+            Type::Array(base, _) => base.align(),
+            Type::Struct(st) => st.layout.borrow().as_ref().map_or(1, |l| l.align),
+            Type::Const(ty) => ty.align(),
+            _ => self.size(),
+        }
+    }
+
+    pub fn is_integer(&self) -> bool {
+        matches!(
+            self,
+            Type::Char
+                | Type::Short
+                | Type::Int
+                | Type::Long
+                | Type::UChar
+                | Type::UShort
+                | Type::UInt
+                | Type::ULong
+        )
+    }
+
+    pub fn is_flonum(&self) -> bool {
+        matches!(self, Type::Float | Type::Double)
+    }
+
+    // Tells whether the type takes part in arithmetic conversions.
+    pub fn is_numeric(&self) -> bool {
+        self.is_integer() || self.is_flonum()
+    }
+
+    // Tells whether the type has a single value that can be tested for
+    // zero, which is every type but a struct or union.
+    pub fn is_scalar(&self) -> bool {
+        !matches!(self, Type::Struct(_))
+    }
+
+    // Tells whether values of the type are compared and divided as unsigned
+    // numbers, which includes pointers.
+    pub fn is_unsigned(&self) -> bool {
+        matches!(
+            self,
+            Type::UChar | Type::UShort | Type::UInt | Type::ULong | Type::Ptr(_)
+        )
+    }
+
+    // Tells whether the size of the type is known, which is false for a
+    // struct that has only been declared, or an array of one.
+    pub fn is_complete(&self) -> bool {
+        match self {
+            Type::Array(base, _) | Type::Const(base) => base.is_complete(),
+            Type::Struct(st) => st.layout.borrow().is_some(),
+            _ => true,
+        }
+    }
+
+    // The type pointed to, if this is a pointer or an array that decays to one.
+    pub fn base(&self) -> Option<&Type> {
+        match self {
+            Type::Ptr(base) | Type::Array(base, _) => Some(base),
+            _ => None,
+        }
+    }
+
+    pub fn member(&self, name: &str) -> Option<Member> {
+        match self {
+            Type::Struct(st) => st
+                .layout
+                .borrow()
+                .as_ref()?
+                .members
+                .iter()
+                .find(|m| m.name == name)
+                .cloned(),
+            Type::Const(ty) => ty.member(name),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum StructKind {
+    Struct,
+    Union,
+}
+
+// A struct or union type. Each definition is a distinct type, so two of them
+// are equal only if they are the same definition. The layout is filled in
+// once the members are known, which lets a member point to its own struct.
+pub struct StructType {
+    pub kind: StructKind,
+    pub tag: Option<String>,
+    layout: RefCell<Option<StructLayout>>,
+}
+
+#[derive(Debug)]
+struct StructLayout {
+    members: Vec<Member>,
+    size: i32,
+    align: i32,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Member {
+    pub name: String,
+    pub ty: Type,
+    // Distance from the start of the struct.
+    pub offset: i32,
+}
+
+impl StructType {
+    pub fn new(kind: StructKind, tag: Option<String>) -> Self {
+        StructType {
+            kind,
+            tag,
+            layout: RefCell::new(None),
+        }
+    }
+
+    // Lays out `members` in order, padding each one to its alignment.
+    // The members of a union all start at offset 0. Fails with the index of
+    // the first member that would end past `MAX_SIZE`, leaving the struct
+    // incomplete.
+    pub fn define(&self, members: Vec<(String, Type)>) -> Result<(), usize> {
+        let mut offset = 0;
+        let mut size = 0;
+        let mut align = 1;
+        let mut laid_out = Vec::<Member>::new();
+        for (i, (name, ty)) in members.into_iter().enumerate() {
+            if self.kind == StructKind::Struct {
+                offset = align_to(offset, ty.align());
+            }
+            let end = match offset.checked_add(ty.size()) {
+                Some(end) if end <= MAX_SIZE => end,
+                _ => return Err(i),
+            };
+            align = align.max(ty.align());
+            size = size.max(end);
+            laid_out.push(Member { name, ty, offset });
+            if self.kind == StructKind::Struct {
+                offset = end;
+            }
+        }
+        *self.layout.borrow_mut() = Some(StructLayout {
+            members: laid_out,
+            size: align_to(size, align),
+            align,
+        });
+        Ok(())
+    }
+
+    pub fn members(&self) -> Vec<Member> {
+        self.layout
+            .borrow()
+            .as_ref()
+            .map_or_else(Vec::new, |l| l.members.clone())
+    }
+}
+
+impl PartialEq for StructType {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+// Prints only the tag, since the members may refer back to the struct.
+impl fmt::Debug for StructType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let kind = match self.kind {
+            StructKind::Struct => "struct",
+            StructKind::Union => "union",
+        };
+        match &self.tag {
+            Some(tag) => write!(f, "{} {}", kind, tag),
+            None => write!(f, "{} <anonymous>", kind),
+        }
+    }
+}
+
+// Spells the type roughly as C does, for diagnostics.
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Type::Char => write!(f, "char"),
+            Type::Short => write!(f, "short"),
+            Type::Int => write!(f, "int"),
+            Type::Long => write!(f, "long"),
+            Type::UChar => write!(f, "unsigned char"),
+            Type::UShort => write!(f, "unsigned short"),
+            Type::UInt => write!(f, "unsigned int"),
+            Type::ULong => write!(f, "unsigned long"),
+            Type::Float => write!(f, "float"),
+            Type::Double => write!(f, "double"),
+            Type::Ptr(base) => write!(f, "{} *", base),
+            Type::Array(base, len) => write!(f, "{}[{}]", base, len),
+            Type::Struct(st) => write!(f, "{:?}", st),
+            Type::Const(ty) => write!(f, "const {}", ty),
+        }
+    }
+}
+
+pub fn align_to(n: i32, align: i32) -> i32 {
+    (n + align - 1) / align * align
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_struct_layout() {
+        let st = StructType::new(StructKind::Struct, Some("s".to_string()));
+        st.define(vec![
+            ("a".to_string(), Type::Char),
+            ("b".to_string(), Type::Int),
+            ("c".to_string(), Type::Char),
+        ])
+        .unwrap();
+        let ty = Type::Struct(Rc::new(st));
+        let offsets: Vec<_> = ["a", "b", "c"]
+            .iter()
+            .map(|m| ty.member(m).unwrap().offset)
+            .collect();
+        assert_eq!(offsets, vec![0, 4, 8]);
+        assert_eq!(ty.size(), 12);
+        assert_eq!(ty.align(), 4);
+    }
+
+    #[test]
+    fn test_union_layout() {
+        let st = StructType::new(StructKind::Union, None);
+        st.define(vec![
+            ("a".to_string(), Type::array_of(Type::Char, 5)),
+            ("b".to_string(), Type::Int),
+        ])
+        .unwrap();
+        let ty = Type::Struct(Rc::new(st));
+        assert_eq!(ty.member("a").unwrap().offset, 0);
+        assert_eq!(ty.member("b").unwrap().offset, 0);
+        assert_eq!(ty.size(), 8);
+        assert_eq!(ty.align(), 4);
+    }
+
+    #[test]
+    fn test_incomplete_struct() {
+        let st = Rc::new(StructType::new(
+            StructKind::Struct,
+            Some("node".to_string()),
+        ));
+        let ty = Type::Struct(st.clone());
+        assert!(!ty.is_complete());
+        st.define(vec![("next".to_string(), Type::pointer_to(ty.clone()))])
+            .unwrap();
+        assert!(ty.is_complete());
+        assert_eq!(ty.size(), 8);
+        assert_eq!(ty.member("next").unwrap().ty, Type::pointer_to(ty.clone()));
+    }
+
+    #[test]
+    fn test_too_large() {
+        let big = Type::checked_array_of(Type::Int, (MAX_SIZE / 4) as usize).unwrap();
+        assert_eq!(big.size(), MAX_SIZE);
+        assert_eq!(Type::checked_array_of(Type::Int, MAX_SIZE as usize), None);
+        assert_eq!(Type::checked_array_of(Type::Char, usize::MAX), None);
+        let st = StructType::new(StructKind::Struct, None);
+        let members = vec![
+            ("a".to_string(), Type::Char),
+            ("b".to_string(), big.clone()),
+            ("c".to_string(), big),
+        ];
+        assert_eq!(st.define(members), Err(1));
+        assert!(!Type::Struct(Rc::new(st)).is_complete());
+    }
+
+    #[test]
+    fn test_const() {
+        let ty = Type::const_of(Type::array_of(Type::Int, 3));
+        assert_eq!(ty, Type::array_of(Type::const_of(Type::Int), 3));
+        assert_eq!(ty.size(), 12);
+        let ty = Type::const_of(Type::const_of(Type::Long));
+        assert_eq!(ty, Type::Const(Box::new(Type::Long)));
+        assert_eq!(ty.unqualified(), &Type::Long);
+        assert_eq!(ty.align(), 8);
+    }
+}
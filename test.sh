@@ -5,7 +5,7 @@ try() {
   input="$2"
 
   ./target/debug/rchan "$input" > tmp.s
-  gcc -o tmp tmp.s
+  gcc -o tmp tmp.s tmp2.o -lm
   ./tmp
   actual="$?"
 
@@ -18,48 +18,816 @@ try() {
   fi
 }
 
+try_output() {
+  expected="$1"
+  input="$2"
+
+  ./target/debug/rchan "$input" > tmp.s
+  gcc -o tmp tmp.s tmp2.o -lm
+  actual="$(./tmp)"
+
+  if [ "$actual" = "$expected" ]; then
+    echo "$input => $actual"
+  else
+    echo "$input => \"$expected\" expected, but got \"$actual\""
+    exit 1
+  fi
+}
+
+fail() {
+  input="$1"
+
+  ./target/debug/rchan "$input" > /dev/null 2>&1
+  actual="$?"
+  if [ "$actual" = "0" ]; then
+    echo "$input => error expected, but compiled"
+    exit 1
+  fi
+  # A panic exits with 101 rather than reporting the error.
+  if [ "$actual" != "1" ]; then
+    echo "$input => error expected, but exited with $actual"
+    exit 1
+  fi
+  echo "$input => error"
+}
+
+fail_with() {
+  expected="$1"
+  input="$2"
+
+  actual="$(./target/debug/rchan "$input" 2>&1 > /dev/null)"
+  if [ "$actual" = "$expected" ]; then
+    echo "$input => error"
+  else
+    echo "$input => \"$expected\" expected, but got \"$actual\""
+    exit 1
+  fi
+}
+
 cargo build
 if [ "$?" = "101" ]; then
   exit 1
 fi
 
-try 0 '0;'
-try 42 '42;'
-try 21 '5+20-4;'
-try 156 '123-56+89;'
-try 45 '1+2+3+4+5+6+7+8+9;'
-try 15 '1+2-3+4-5+6-7+8+9;'
-try 189 '123 - 23 + 89;'
-try 2 '1 - 2 + 3;'
-try 47 '5+6*7;'
-try 15 '5*(9-6);'
-try 4 '(3+5)/2;'
-try 10 '-10+20;'
-try 1 '2*-2+5;'
-try 10 '+5++5;'
-try 1 '1 == 1;'
-try 0 '1 == 2;'
-try 1 '1 != 2;'
-try 0 '1 != 1;'
-try 1 '2 >= 1;'
-try 1 '1 >= 1;'
-try 0 '1 >= 2;'
-try 1 '1 > 0;'
-try 0 '1 > 2;'
-try 1 '1 < 2;'
-try 0 '1 < 1;'
-try 0 '2 <= 1;'
-try 1 '1 <= 1;'
-try 0 '1 <= 0;'
-try 1 '12 + 13 <= 10 * 5;'
-try 1 'a=1; a;'
-try 1 'a=1; b=2; b-a;'
-try 16 'a=8; b=2; a*b;'
-try 65 'a=8; a=a*a; a+1;'
-try 4 'abc=8; def=abc*2; def/4;'
-try 14 'a = 3;
-b = 5 * 6 - 8;
-a + b / 2;'
-try 1 'return 1;'
-try 1 'a = 1; b= 2; return a * b - 1;'
+cat <<EOF | gcc -xc -c -o tmp2.o -
+int ret3() { return 3; }
+int ret5() { return 5; }
+int add(int x, int y) { return x + y; }
+int sub(int x, int y) { return x - y; }
+int add6(int a, int b, int c, int d, int e, int f) {
+  return a + b + c + d + e + f;
+}
+void alloc4(int **p, int a, int b, int c, int d) {
+  *p = (int *)__builtin_malloc(sizeof(int) * 4);
+  (*p)[0] = a; (*p)[1] = b; (*p)[2] = c; (*p)[3] = d;
+}
+double add_double(double x, double y) { return x + y; }
+float add_float(float x, float y) { return x + y; }
+double mix(int a, double b, long c, float d) { return a * b + c * d; }
+EOF
+
+try 0 'int main() { return 0; }'
+try 42 'int main() { return 42; }'
+try 21 'int main() { return 5+20-4; }'
+try 156 'int main() { return 123-56+89; }'
+try 45 'int main() { return 1+2+3+4+5+6+7+8+9; }'
+try 15 'int main() { return 1+2-3+4-5+6-7+8+9; }'
+try 189 'int main() { return 123 - 23 + 89; }'
+try 2 'int main() { return 1 - 2 + 3; }'
+try 47 'int main() { return 5+6*7; }'
+try 15 'int main() { return 5*(9-6); }'
+try 4 'int main() { return (3+5)/2; }'
+try 10 'int main() { return -10+20; }'
+try 1 'int main() { return 2*-2+5; }'
+try 10 'int main() { return +5+ +5; }'
+try 1 'int main() { return 1 == 1; }'
+try 0 'int main() { return 1 == 2; }'
+try 1 'int main() { return 1 != 2; }'
+try 0 'int main() { return 1 != 1; }'
+try 1 'int main() { return 2 >= 1; }'
+try 1 'int main() { return 1 >= 1; }'
+try 0 'int main() { return 1 >= 2; }'
+try 1 'int main() { return 1 > 0; }'
+try 0 'int main() { return 1 > 2; }'
+try 1 'int main() { return 1 < 2; }'
+try 0 'int main() { return 1 < 1; }'
+try 0 'int main() { return 2 <= 1; }'
+try 1 'int main() { return 1 <= 1; }'
+try 0 'int main() { return 1 <= 0; }'
+try 1 'int main() { return 12 + 13 <= 10 * 5; }'
+try 1 'int main() { int a=1; return a; }'
+try 1 'int main() { int a=1; int b=2; return b-a; }'
+try 16 'int main() { int a=8; int b=2; return a*b; }'
+try 65 'int main() { int a=8; a=a*a; return a+1; }'
+try 4 'int main() { int abc=8; int def=abc*2; return def/4; }'
+try 14 'int main() { int a = 3;
+int b = 5 * 6 - 8;
+return a + b / 2; }'
+try 1 'int main() { return 1; }'
+try 1 'int main() { int a = 1; int b= 2; return a * b - 1; }'
+try 3 'int main() { if (0) return 2; return 3; }'
+try 3 'int main() { if (1-1) return 2; return 3; }'
+try 2 'int main() { if (1) return 2; return 3; }'
+try 2 'int main() { if (2-1) return 2; return 3; }'
+try 4 'int main() { if (0) return 3; else return 4; }'
+try 3 'int main() { if (1) return 3; else return 4; }'
+try 5 'int main() { int a = 0; if (a == 1) a = 3; else if (a == 0) a = 5; else a = 7; return a; }'
+try 7 'int main() { int a = 2; if (a == 1) a = 3; else if (a == 0) a = 5; else a = 7; return a; }'
+try 10 'int main() { int i = 0; while (i < 10) i = i + 1; return i; }'
+try 0 'int main() { int i = 0; while (0) i = i + 1; return i; }'
+try 55 'int main() { int j = 0; for (int i = 0; i <= 10; i = i + 1) j = i + j; return j; }'
+try 3 'int main() { for (;;) return 3; return 5; }'
+try 5 'int main() { int i = 0; for (;;) if ((i = i + 1) == 5) break; return i; }'
+try 7 'int main() { int i = 0; while (1) if ((i = i + 1) == 7) break; return i; }'
+try 25 'int main() { int j = 0; for (int i = 0; i < 10; i = i + 1) if (i - i / 2 * 2 == 0) continue; else j = j + i; return j; }'
+try 5 'int main() { int i = 0; int j = 0; while (i < 10) if ((i = i + 1) > 5) continue; else j = j + 1; return j; }'
+try 30 'int main() { int n = 0; for (int i = 0; i < 5; i = i + 1) for (int j = 0; j < 10; j = j + 1) if (j == 6) break; else n = n + 1; return n; }'
+try 3 'int main() { { 1; { 2; } return 3; } }'
+try 10 'int main() { int i = 0; while (i < 10) { i = i + 1; } return i; }'
+try 55 'int main() { int i = 0; int j = 0; while (i <= 10) { j = i + j; i = i + 1; } return j; }'
+try 6 'int main() { int a = 1; { a = a + 2; { a = a * 2; } } return a; }'
+try 6 'int main() { int x = 0; for (int i = 0; i < 4; i = i + 1) { if (i == 2) { continue; } x = x + 2; } return x; }'
+try 3 'int main() { int x = 0; while (1) { x = x + 1; if (x == 3) { break; } } return x; }'
+try 8 'int one() { return 1; } int main() { return 8; }'
+try 5 'int main() { return 5; } int two() { return 2; }'
+try 3 'int main(int argc, int argv) { int a = 3; return a; }'
+try 7 'int f(int a, int b, int c, int d, int e, int f) { return a; } int main() { return 7; }'
+try 3 'int main() { return ret3(); }'
+try 5 'int main() { return ret5(); }'
+try 8 'int main() { return add(3, 5); }'
+try 2 'int main() { return sub(5, 3); }'
+try 21 'int main() { return add6(1, 2, 3, 4, 5, 6); }'
+try 66 'int main() { return add6(1, 2, add6(3, 4, 5, 6, 7, 8), 9, 10, 11); }'
+try 136 'int main() { return add6(1, 2, add6(3, add6(4, 5, 6, 7, 8, 9), 10, 11, 12, 13), 14, 15, 16); }'
+try 7 'int main() { int a = 1; int b = add(a, 2); return add(a + b, ret3()); }'
+try 3 'int main() { return abs(0 - 3); }'
+try 9 'int main() { exit(9); return 1; }'
+try 32 'int ret32() { return 32; } int main() { return ret32(); }'
+try 7 'int main() { return add2(3, 4); } int add2(int x, int y) { return x + y; }'
+try 1 'int main() { return sub2(4, 3); } int sub2(int x, int y) { return x - y; }'
+try 55 'int fib(int x) { if (x <= 1) return x; return fib(x - 1) + fib(x - 2); } int main() { return fib(10); }'
+try 21 'int sum6(int a, int b, int c, int d, int e, int f) { return a + b + c + d + e + f; } int main() { return sum6(1, 2, 3, 4, 5, 6); }'
+try 29 'int main() { int v0 = 0; int v1 = 1; int v2 = 2; int v3 = 3; int v4 = 4; int v5 = 5; int v6 = 6; int v7 = 7; int v8 = 8; int v9 = 9; int v10 = 10; int v11 = 11; int v12 = 12; int v13 = 13; int v14 = 14; int v15 = 15; int v16 = 16; int v17 = 17; int v18 = 18; int v19 = 19; int v20 = 20; int v21 = 21; int v22 = 22; int v23 = 23; int v24 = 24; int v25 = 25; int v26 = 26; int v27 = 27; int v28 = 28; int v29 = 29; return v0 + v29; }'
+try 45 'int f(int a, int b, int c, int d, int e, int f) { int x = a + b; int y = c + d; int z = e + f; return x + y + z; } int main() { int a = 1; int b = f(1, 2, 3, 4, 5, 6); return a + b + ret3() * 7 + 2; }'
+try 3 'int main() { int a; int b; a = b = 3; return a; }'
+try 5 'int main() { int a = 2, b = 3, c; c = a + b; return c; }'
+try 8 'int main() { int x = 3; { int x = 5; x = x + 3; return x; } }'
+try 3 'int main() { int x = 3; { int x = 5; } return x; }'
+try 7 'int main() { int x = 7; for (int x = 0; x < 3; x = x + 1) 1; return x; }'
+try 10 'int main() { int s = 0; for (int i = 0; i < 5; i = i + 1) s = s + i; for (int i = 0; i < 1; i = i + 1) s = s + 0; return s; }'
+try 3 'int main() { int x = 3; int *y = &x; return *y; }'
+try 3 'int main() { int x = 3; int *y = &x; int **z = &y; return **z; }'
+try 5 'int main() { int x = 3; int *y = &x; *y = 5; return x; }'
+try 7 'int main() { int x = 3; int *y = &x; int **z = &y; **z = 7; return x; }'
+try 3 'int main() { int x = 3; int y = 5; return *(&y + 1); }'
+try 5 'int main() { int x = 3; int y = 5; return *(&x - 1); }'
+try 7 'int main() { int x = 3; int y = 5; *(&y + 1) = 7; return x; }'
+try 1 'int main() { int x; int y; return &x - &y; }'
+try 3 'int main() { int x = 3; return *&x; }'
+try 2 'int main() { int *p; alloc4(&p, 1, 2, 4, 8); int *q = p + 1; return *q; }'
+try 8 'int main() { int *p; alloc4(&p, 1, 2, 4, 8); int *q = p + 3; return *q; }'
+try 4 'int main() { int *p; alloc4(&p, 1, 2, 4, 8); int *q = 2 + p; return *q; }'
+try 3 'int main() { int *p; alloc4(&p, 1, 2, 4, 8); int *q = p + 3; return q - p; }'
+try 4 'int main() { int *p; alloc4(&p, 1, 2, 4, 8); int *q = p + 3; return *(q - 1); }'
+try 9 'int main() { int *p; alloc4(&p, 1, 2, 4, 8); *(p + 2) = 9; return *(p + 2); }'
+try 6 'int deref(int *p) { return *p; } int main() { int x = 6; return deref(&x); }'
+try 5 'int set(int *p, int v) { *p = v; return 0; } int main() { int x = 1; set(&x, 5); return x; }'
+try 1 'int main() { int x; int *p = &x; int *q = &x; return p == q; }'
+try 3 'int main() { int x[2]; int *y = &x; *y = 3; return *x; }'
+try 3 'int main() { int x[3]; *x = 3; *(x + 1) = 4; *(x + 2) = 5; return *x; }'
+try 4 'int main() { int x[3]; *x = 3; *(x + 1) = 4; *(x + 2) = 5; return *(x + 1); }'
+try 5 'int main() { int x[3]; *x = 3; *(x + 1) = 4; *(x + 2) = 5; return *(x + 2); }'
+try 0 'int main() { int x[2][3]; int *y = x; *y = 0; return **x; }'
+try 1 'int main() { int x[2][3]; int *y = x; *(y + 1) = 1; return *(*x + 1); }'
+try 2 'int main() { int x[2][3]; int *y = x; *(y + 2) = 2; return *(*x + 2); }'
+try 3 'int main() { int x[2][3]; int *y = x; *(y + 3) = 3; return **(x + 1); }'
+try 4 'int main() { int x[2][3]; int *y = x; *(y + 4) = 4; return *(*(x + 1) + 1); }'
+try 5 'int main() { int x[2][3]; int *y = x; *(y + 5) = 5; return *(*(x + 1) + 2); }'
+try 3 'int main() { int x[3]; x[0] = 3; x[1] = 4; x[2] = 5; return x[0]; }'
+try 5 'int main() { int x[3]; x[0] = 3; x[1] = 4; x[2] = 5; return x[2]; }'
+try 5 'int main() { int x[3]; x[0] = 3; x[1] = 4; 2[x] = 5; return *(x + 2); }'
+try 5 'int main() { int x[2][3]; int *y = x; y[5] = 5; return x[1][2]; }'
+try 4 'int main() { int x[2][3]; x[1][1] = 4; return *(x[1] + 1); }'
+try 45 'int main() { int a[10]; for (int i = 0; i < 10; i = i + 1) a[i] = i; int s = 0; for (int i = 0; i < 10; i = i + 1) s = s + a[i]; return s; }'
+try 12 'int main() { int a[3][4]; for (int i = 0; i < 3; i = i + 1) for (int j = 0; j < 4; j = j + 1) a[i][j] = i * j; return a[2][3] + a[1][3] * 1 + a[2][0] + 3; }'
+try 6 'int sum(int *a, int n) { int s = 0; for (int i = 0; i < n; i = i + 1) s = s + a[i]; return s; } int main() { int a[3]; a[0] = 1; a[1] = 2; a[2] = 3; return sum(a, 3); }'
+try 7 'int second(int a[2]) { return a[1]; } int main() { int a[2]; a[0] = 5; a[1] = 7; return second(a); }'
+try 2 'int main() { int a[4]; int *p = a + 3; return p - (a + 1); }'
+try 4 'int main() { int x; return sizeof(x); }'
+try 4 'int main() { int x; return sizeof x; }'
+try 8 'int main() { int *x; return sizeof(x); }'
+try 16 'int main() { int x[4]; return sizeof(x); }'
+try 48 'int main() { int x[3][4]; return sizeof(x); }'
+try 16 'int main() { int x[3][4]; return sizeof(*x); }'
+try 4 'int main() { int x[3][4]; return sizeof(**x); }'
+try 5 'int main() { int x[3][4]; return sizeof(**x) + 1; }'
+try 5 'int main() { int x[3][4]; return sizeof **x + 1; }'
+try 4 'int main() { int x[3][4]; return sizeof(**x + 1); }'
+try 4 'int main() { int x = 1; return sizeof(x = 2); }'
+try 1 'int main() { int x = 1; sizeof(x = 2); return x; }'
+try 4 'int main() { return sizeof(int); }'
+try 8 'int main() { return sizeof(int *); }'
+try 40 'int main() { return sizeof(int[2][5]); }'
+try 24 'int main() { return sizeof(int *[3]); }'
+try 16 'int main() { int x[4]; return sizeof(x)[0] * 4; }'
+try 8 'int main() { int x[2][4]; return sizeof (&x); }'
+try 32 'int main() { int x[2][4]; return (&x + 1) - &x + sizeof(*&x) - 1 + 0 * 0; }'
+try 1 'int main() { char x = 1; return x; }'
+try 1 'int main() { char x = 1; char y = 2; return x; }'
+try 2 'int main() { char x = 1; char y = 2; return y; }'
+try 1 'int main() { char x; return sizeof(x); }'
+try 10 'int main() { char x[10]; return sizeof(x); }'
+try 1 'int sub_char(char a, char b, char c) { return a - b - c; } int main() { return sub_char(7, 3, 3); }'
+try 3 'int main() { char x[3]; x[0] = -1; x[1] = 2; int y = 4; return x[0] + y; }'
+try 97 'int main() { return "abc"[0]; }'
+try 98 'int main() { return "abc"[1]; }'
+try 99 'int main() { return "abc"[2]; }'
+try 0 'int main() { return "abc"[3]; }'
+try 4 'int main() { return sizeof("abc"); }'
+try 7 'int main() { return "\a"[0]; }'
+try 10 'int main() { return "\n"[0]; }'
+try 27 'int main() { return "\e"[0]; }'
+try 34 'int main() { return "\""[0]; }'
+try 92 'int main() { return "\\\\"[0]; }'
+try 0 'int main() { return "\0"[0]; }'
+try 16 'int main() { return "\20"[0]; }'
+try 65 'int main() { return "\101"[0]; }'
+try 104 'int main() { return "\1500"[0]; }'
+try 48 'int main() { return "\1500"[1]; }'
+try 0 'int main() { return "\x00"[0]; }'
+try 119 'int main() { return "\x77"[0]; }'
+try 165 'int main() { return "\xA5"[0] + 256; }'
+try 5 'int len(char *s) { int n = 0; while (s[n]) n = n + 1; return n; } int main() { return len("hello"); }'
+try 3 'int main() { char *s = "abc"; char *t = "abc"; return (s != t) + 2; }'
+try_output 'hello, world' 'int main() { printf("hello, world\n"); return 0; }'
+try_output '42' 'int main() { int x = 42; printf("%d\n", x); return 0; }'
+try_output '1 2 3' 'int main() { printf("%d %d %d\n", 1, 2, 3); return 0; }'
+try_output 'abc' 'int main() { char s[4]; s[0] = 97; s[1] = 98; s[2] = 99; s[3] = 0; printf("%s\n", s); return 0; }'
+try_output '0 1 2' 'int main() { for (int i = 0; i < 3; i = i + 1) { if (i) printf(" "); printf("%d", i); } printf("\n"); return 0; }'
+try 0 'int x; int main() { return x; }'
+try 3 'int x; int main() { x = 3; return x; }'
+try 7 'int x; int y; int main() { x = 3; y = 4; return x + y; }'
+try 7 'int x, y; int main() { x = 3; y = 4; return x + y; }'
+try 0 'int x[4]; int main() { x[0] = 0; x[1] = 1; x[2] = 2; x[3] = 3; return x[0]; }'
+try 3 'int x[4]; int main() { x[0] = 0; x[1] = 1; x[2] = 2; x[3] = 3; return x[3]; }'
+try 4 'int x; int main() { return sizeof(x); }'
+try 16 'int x[4]; int main() { return sizeof(x); }'
+try 5 'int x = 5; int main() { return x; }'
+try 3 'int x = 1 + 2 * 3 - 4; int main() { return x; }'
+try 6 'int a[3] = {1, 2, 3}; int main() { return a[0] + a[1] + a[2]; }'
+try 0 'int a[4] = {1, 2}; int main() { return a[3]; }'
+try 98 'char s[4] = "abc"; int main() { return s[1]; }'
+try 99 'char *s = "abc"; int main() { return s[2]; }'
+try 7 'int x = 7; int *p = &x; int main() { return *p; }'
+try 3 'int a[3] = {1, 2, 3}; int *p = a + 2; int main() { return *p; }'
+try 2 'int a[3] = {1, 2, 3}; int *p = &a[2] - 1; int main() { return *p; }'
+try 5 'int x; int *p = &x; int main() { *p = 5; return x; }'
+try 2 'int x = 1; int main() { int x = 2; return x; }'
+try 1 'int x = 1; int main() { { int x = 2; } return x; }'
+try 3 'int x; int f() { x = x + 1; return x; } int main() { f(); f(); return f(); }'
+try 8 'int g; int main() { int x = 3; g = 5; return x + g; } int h;'
+try_output 'abc 42' 'char msg[4] = "abc"; int n = 42; int main() { printf("%s %d\n", msg, n); return 0; }'
+try 1 'int main() { struct {int a; int b;} x; x.a=1; x.b=2; return x.a; }'
+try 2 'int main() { struct {int a; int b;} x; x.a=1; x.b=2; return x.b; }'
+try 1 'int main() { struct {char a; int b; char c;} x; x.a=1; x.b=2; x.c=3; return x.a; }'
+try 3 'int main() { struct {char a; int b; char c;} x; x.b=1; x.b=2; x.c=3; return x.c; }'
+try 0 'int main() { struct {char a; char b;} x[3]; char *p=x; p[0]=0; return x[0].a; }'
+try 1 'int main() { struct {char a; char b;} x[3]; char *p=x; p[1]=1; return x[0].b; }'
+try 2 'int main() { struct {char a; char b;} x[3]; char *p=x; p[2]=2; return x[1].a; }'
+try 3 'int main() { struct {char a; char b;} x[3]; char *p=x; p[3]=3; return x[1].b; }'
+try 6 'int main() { struct {char a[3]; char b[5];} x; char *p=&x; x.a[0]=6; return p[0]; }'
+try 7 'int main() { struct {char a[3]; char b[5];} x; char *p=&x; x.b[0]=7; return p[3]; }'
+try 6 'int main() { struct { struct { char b; } a; } x; x.a.b=6; return x.a.b; }'
+try 4 'int main() { struct {int a;} x; return sizeof(x); }'
+try 8 'int main() { struct {int a; int b;} x; return sizeof(x); }'
+try 8 'int main() { struct {int a, b;} x; return sizeof(x); }'
+try 12 'int main() { struct {int a[3];} x; return sizeof(x); }'
+try 16 'int main() { struct {int a;} x[4]; return sizeof(x); }'
+try 24 'int main() { struct {int a[3];} x[2]; return sizeof(x); }'
+try 2 'int main() { struct {char a; char b;} x; return sizeof(x); }'
+try 0 'int main() { struct {} x; return sizeof(x); }'
+try 8 'int main() { struct {char a; int b;} x; return sizeof(x); }'
+try 8 'int main() { struct {int a; char b;} x; return sizeof(x); }'
+try 16 'int main() { struct {char a; int *b;} x; return sizeof(x); }'
+try 4 'int main() { struct {char a; int b;} x; return _Alignof(struct {char a; int b;}); }'
+try 8 'int main() { return _Alignof(struct {char a; int *b;}); }'
+try 1 'int main() { return _Alignof(char[5]); }'
+try 8 'int main() { struct t {int a; int b;} x; struct t y; return sizeof(y); }'
+try 8 'int main() { struct t {int a; int b;}; struct t y; return sizeof(y); }'
+try 2 'int main() { struct t {char a[2];}; { struct t {char a[4];}; } struct t y; return sizeof(y); }'
+try 3 'int main() { struct t {int x;}; int t=1; struct t y; y.x=2; return t+y.x; }'
+try 3 'int main() { struct t {char a;} x; struct t *y = &x; x.a=3; return y->a; }'
+try 3 'int main() { struct t {char a;} x; struct t *y = &x; y->a=3; return x.a; }'
+try 5 'struct node { int val; struct node *next; }; int main() { struct node a, b; a.val = 2; b.val = 3; a.next = &b; b.next = 0; return a.val + a.next->val; }'
+try 3 'int main() { struct {int a,b;} x,y; x.a=3; y=x; return y.a; }'
+try 7 'int main() { struct t {int a,b;}; struct t x; x.a=7; struct t y; struct t *z=&y; *z=x; return y.a; }'
+try 7 'int main() { struct t {int a,b;}; struct t x; x.a=7; struct t y, *p=&x, *q=&y; *q=*p; return y.a; }'
+try 5 'int main() { struct t {char a, b;} x, y; x.a=5; y=x; return y.a; }'
+try 3 'int main() { struct {int a,b;} x,y; x.a=3; y=x; x.a=4; return y.a; }'
+try 8 'int main() { union { int a; char b[6]; } x; return sizeof(x); }'
+try 3 'int main() { union { int a; char b[4]; } x; x.a = 515; return x.b[0]; }'
+try 2 'int main() { union { int a; char b[4]; } x; x.a = 515; return x.b[1]; }'
+try 0 'int main() { union { int a; char b[4]; } x; x.a = 515; return x.b[2]; }'
+try 4 'int main() { union { struct { char a, b; } c; int d; } x; x.d = 1028; return x.c.b; }'
+try 3 'struct s { int a; char b; } g; int main() { g.a = 1; g.b = 2; return g.a + g.b; }'
+try 12 'struct { int a; char b[4]; int c; } g = {1, "ab", 9}; int main() { return g.a + g.b[1] - 98 + g.c + 2; }'
+try 3 'union { int a; char b[4]; } g = {515}; int main() { return g.b[0]; }'
+try 6 'struct p { int x, y; } ps[2] = {{1, 2}, {3}}; int main() { return ps[0].x + ps[0].y + ps[1].x + ps[1].y; }'
+try 7 'int sum(struct { int a, b; } *p) { return p->a + p->b; } int main() { return 7; }'
+try 2 'int main() { short x; return sizeof(x); }'
+try 8 'int main() { long x; return sizeof(x); }'
+try 8 'int main() { long long x; return sizeof(x); }'
+try 4 'int main() { unsigned x; return sizeof(x); }'
+try 2 'int main() { return sizeof(short int); }'
+try 2 'int main() { return sizeof(int short); }'
+try 8 'int main() { return sizeof(long int); }'
+try 8 'int main() { return sizeof(unsigned long long int); }'
+try 8 'int main() { return sizeof(long unsigned); }'
+try 1 'int main() { return sizeof(signed char); }'
+try 4 'int main() { return sizeof(signed); }'
+try 8 'int main() { return sizeof(1L); }'
+try 4 'int main() { return sizeof(1u); }'
+try 8 'int main() { return sizeof(2147483648); }'
+try 4 'int main() { return sizeof(0x80000000); }'
+try 8 'int main() { return sizeof(1 + 1L); }'
+try 4 'int main() { char x; return sizeof(x + x); }'
+try 8 'int main() { int *p; return sizeof(p - p); }'
+try 1 'int main() { short x = 1; return x; }'
+try 7 'int main() { short x[3]; x[0] = 1; x[1] = 2; x[2] = 4; return x[0] + x[1] + x[2]; }'
+try 3 'int main() { long x = 3; return x; }'
+try 1 'int main() { long x = 4294967296; return x == 4294967296; }'
+try 1 'int main() { long x = 5000000000; return x / 1000000000 == 5; }'
+try 1 'int main() { return 0x7fffffffffffffff > 0; }'
+try 255 'int main() { unsigned char x = 255; return x; }'
+try 1 'int main() { unsigned char x = 255; return x > 0; }'
+try 1 'int main() { char x = 255; return x < 0; }'
+try 1 'int main() { unsigned short x = 65535; return x == 65535; }'
+try 1 'int main() { short x = 65535; return x == -1; }'
+try 1 'int main() { unsigned x = -1; return x == 4294967295; }'
+try 1 'int main() { unsigned x = -1; return x > 0; }'
+try 0 'int main() { return -1 < 1u; }'
+try 1 'int main() { return -1 < 1; }'
+try 1 'int main() { return -1L < 1u; }'
+try 0 'int main() { return -1 < 1ul; }'
+try 1 'int main() { return 0xffffffff == -1u; }'
+try 1 'int main() { return -1 == 4294967295u; }'
+try 0 'int main() { return -1L == 4294967295u; }'
+try 1 'int main() { unsigned x = 4294967295; return x + 1 == 0; }'
+try 1 'int main() { int x = 2147483647; return x + 1 < 0; }'
+try 1 'int main() { return -7 / 2 == -3; }'
+try 1 'int main() { return -7u / 2 == 2147483644; }'
+try 1 'int main() { unsigned long x = -1; return x / 2 == 9223372036854775807; }'
+try 1 'int main() { long x = -1; return x / 2 == 0; }'
+try 1 'int main() { unsigned char x = 200; unsigned char y = 100; return x + y == 300; }'
+try 1 'int main() { char x = 100; char y = 100; char z = x + y; return z == -56; }'
+try 1 'int main() { return 0x10 + 010 + 0b10 == 26; }'
+try 1 'int main() { return 0XfF == 255; }'
+try 10 'long f(long x) { return x * 2; } int main() { return f(5); }'
+try 1 'long f() { return 5000000000; } int main() { return f() == 5000000000; }'
+try 44 'char f() { return 300; } int main() { return f(); }'
+try 1 'short f(short x) { return x; } int main() { return f(65535) == -1; }'
+try 1 'unsigned f(unsigned a, unsigned b) { return a > b; } int main() { return f(-1, 1); }'
+try 1 'long g = 5000000000; int main() { return g == 5000000000; }'
+try 1 'unsigned short h = 65535; int main() { return h == 65535; }'
+try 1 'short s[2] = {1, -1}; int main() { return s[1] == -1; }'
+try_output '-1 4294967295 18446744073709551615' 'int main() { int a = -1; unsigned b = -1; unsigned long c = -1; printf("%d %u %lu\n", a, b, c); return 0; }'
+try_output '5000000000' 'int main() { long x = 5000000000; printf("%ld\n", x); return 0; }'
+
+try 3 'int main() { double x = 3.7; return x; }'
+try 253 'int main() { float x = -3.7f; return x; }'
+try 1 'int main() { double x = 0.1; return x + 0.2 > 0.3; }'
+try 1 'int main() { float x = 0.1f; return x + 0.2f == 0.3f; }'
+try 1 'int main() { double x = 1e3; return x == 1000; }'
+try 1 'int main() { double x = .5; return 2 * x == 1; }'
+try 1 'int main() { double x = 1.0 / 0.0; return x > 1e308; }'
+try 1 'int main() { double x = 0.0 / 0.0; return x != x; }'
+try 0 'int main() { double x = 0.0 / 0.0; return x == x; }'
+try 0 'int main() { double x = 0.0 / 0.0; return x < 1; }'
+try 0 'int main() { double x = 0.0 / 0.0; return x <= 1; }'
+try 1 'int main() { double x = 0.0 / 0.0; if (x) return 1; return 0; }'
+try 0 'int main() { double x = -0.0; if (x) return 1; return 0; }'
+try 5 'int main() { float x = 0.5f; int i = 0; while (x < 16) { x = x * 2; i = i + 1; } return i; }'
+try 1 'int main() { double x = -2.5; return -x == 2.5; }'
+try 1 'int main() { float x = 2.5f; double y = x; return y == 2.5; }'
+try 1 'int main() { double x = 16777217; float y = x; return y == 16777216; }'
+try 1 'int main() { unsigned long x = 18446744073709551615u; double y = x; return y == 18446744073709551616.0; }'
+try 1 'int main() { double x = 1e19; unsigned long y = x; return y == 10000000000000000000u; }'
+try 1 'int main() { unsigned x = 4294967295u; double y = x; return y == 4294967295.0; }'
+try 1 'int main() { char c = -1; double y = c; return y == -1; }'
+try 1 'int main() { double x = 300.5; unsigned char c = x; return c == 44; }'
+try 7 'double add_double(double x, double y); int main() { return add_double(2.5, 4.5); }'
+try 7 'float add_float(float, float); int main() { return add_float(2.5f, 4.5f); }'
+try 1 'double add_double(double, double); int main() { return add_double(2, 3) == 5; }'
+try 1 'float add_float(float x, float y); int main() { return add_float(1, 2) == 3; }'
+try 12 'double mix(int a, double b, long c, float d); int main() { return mix(2, 2.5, 3, 2.5f); }'
+try 8 'double half(double x) { return x / 2; } int main() { return half(17); }'
+try 4 'float f(int a, float b, int c, double d) { return a + b - c + d; } int main() { return f(1, 2.5f, 3, 3.5); }'
+try 1 'double g = 2.5; float h = -1.25f; int main() { return g + h == 1.25; }'
+try 17 'struct { char c; double d; } s; int main() { s.d = 1.5; return sizeof(s) + (s.d == 1.5); }'
+try 16 'int main() { double a[2]; a[1] = 0.5; return sizeof(a) + a[1]; }'
+try 4 'int main() { float x; return sizeof(x); }'
+try 8 'int main() { return sizeof(double) + sizeof(1.0f) - sizeof(float); }'
+try 1 'int main() { double x = 2.0; double *p = &x; *p = *p * 3; return x == 6; }'
+try_output '3.140000 2.500000' 'int main() { float f = 2.5f; printf("%f %f\n", 3.14, f); return 0; }'
+try_output '1.414214' 'double sqrt(double); int main() { printf("%f\n", sqrt(2)); return 0; }'
+try_output '-1.000000 8.000000' 'double cos(double x); double pow(double, double); int main() { printf("%f %f\n", cos(3.141592653589793), pow(2, 3)); return 0; }'
+try_output '1 2.500000 3 4.500000' 'int main() { printf("%d %f %d %f\n", 1, 2.5, 3, 4.5); return 0; }'
+
+try 2 'int main() { return 17 % 5; }'
+try 1 'int main() { return -17 % 5 + 3; }'
+try 1 'int main() { unsigned x = -1; return x % 10 == 5; }'
+try 1 'int main() { unsigned long x = -1; return x % 10 == 5; }'
+try 8 'int main() { return 12 & 10; }'
+try 14 'int main() { return 12 | 10; }'
+try 6 'int main() { return 12 ^ 10; }'
+try 1 'int main() { return ~0 == -1; }'
+try 1 'int main() { unsigned x = 0; return ~x == 4294967295; }'
+try 1 'int main() { char c = 0; return ~c == -1; }'
+try 40 'int main() { return 5 << 3; }'
+try 5 'int main() { return 40 >> 3; }'
+try 1 'int main() { int x = -16; return x >> 2 == -4; }'
+try 1 'int main() { unsigned x = -16; return x >> 28 == 15; }'
+try 1 'int main() { long x = -1; return x >> 63 == -1; }'
+try 1 'int main() { unsigned long x = -1; return x >> 63 == 1; }'
+try 1 'int main() { char c = -128; return c >> 1 == -64; }'
+try 1 'int main() { unsigned char c = 128; return c << 1 == 256; }'
+try 1 'int main() { long x = 1; return x << 40 == 1099511627776; }'
+try 1 'int main() { int x = 1; return (x << 31 >> 31) == -1; }'
+try 3 'int main() { return 1 + 2 << 1 >> 1; }'
+try 1 'int main() { return 5 & 3 == 3; }'
+try 1 'int main() { return 1 | 2 ^ 3 & 6; }'
+try 1 'int main() { return 2 && 3; }'
+try 0 'int main() { return 2 && 0; }'
+try 1 'int main() { return 0 || 3; }'
+try 0 'int main() { return 0 || 0; }'
+try 1 'int main() { return 0.5 && 1; }'
+try 0 'int main() { return 0.0 || 0; }'
+try 1 'int main() { int *p; int x; p = &x; return p && 1; }'
+try 3 'int main() { int x = 3; 0 && (x = 5); return x; }'
+try 3 'int main() { int x = 3; 1 || (x = 5); return x; }'
+try 5 'int main() { int x = 3; 1 && (x = 5); return x; }'
+try 1 'int main() { return !0; }'
+try 0 'int main() { return !5; }'
+try 1 'int main() { return !0.0; }'
+try 1 'int main() { return !!7; }'
+try 1 'int main() { int *p = 0; return !p; }'
+try 2 'int main() { return 0 ? 1 : 2; }'
+try 1 'int main() { return 3 ? 1 : 2; }'
+try 3 'int main() { return 0 ? 1 : 0 ? 2 : 3; }'
+try 2 'int main() { return 1 ? 2 : 3 ? 4 : 5; }'
+try 5 'int main() { int x = 3; 1 ? (x = 5) : (x = 7); return x; }'
+try 7 'int main() { int x = 3; 0 ? (x = 5) : (x = 7); return x; }'
+try 1 'int main() { return (1 ? -1 : 2u) > 0; }'
+try 3 'int main() { double d = 0 ? 1 : 3.5; return d; }'
+try 98 'int main() { return (1 ? "ab" : "cde")[1]; }'
+try 5 'int main() { int a[2]; int b[3]; b[1] = 5; return (0 ? a : b)[1]; }'
+try 3 'int main() { return (1, 2, 3); }'
+try 5 'int main() { int x = 0; int y = (x = 2, x + 3); return y; }'
+try 10 'int main() { int i; int j; int s = 0; for (i = 0, j = 10; i < j; i = i + 1, j = j - 1) s = s + 2; return s; }'
+try 1 'int main() { return add(1, (2, 3)) == 4; }'
+try 8 'int x = 1 << 3; int main() { return x; }'
+try 1 'int x = 0 || 5; int main() { return x; }'
+try 6 'int x = 1 ? 6 : 7; int main() { return x; }'
+try 250 'int x = ~5 & 255; int main() { return x; }'
+
+try 7 'int main() { int i = 2; i += 5; return i; }'
+try 7 'int main() { int i = 2; return i += 5; }'
+try 3 'int main() { int i = 5; i -= 2; return i; }'
+try 6 'int main() { int i = 3; i *= 2; return i; }'
+try 3 'int main() { int i = 6; i /= 2; return i; }'
+try 2 'int main() { int i = 17; i %= 5; return i; }'
+try 8 'int main() { int i = 12; i &= 10; return i; }'
+try 14 'int main() { int i = 12; i |= 10; return i; }'
+try 6 'int main() { int i = 12; i ^= 10; return i; }'
+try 40 'int main() { int i = 5; i <<= 3; return i; }'
+try 5 'int main() { int i = 40; i >>= 3; return i; }'
+try 1 'int main() { unsigned i = -16; i >>= 28; return i == 15; }'
+try 1 'int main() { int i = -16; i >>= 2; return i == -4; }'
+try 3 'int main() { int i = 1; int j = 2; i += j += 0; return i; }'
+try 3 'int main() { int i = 1; i += 1.9; i *= 1.5; return i; }'
+try 44 'int main() { char c = 0; c += 300; return c; }'
+try 1 'int main() { double d = 1; d /= 4; return d == 0.25; }'
+try 1 'int main() { float f = 1; f += 0.1f; return f == 1.1f; }'
+try 3 'int main() { int a[4]; int *p = a; p += 3; return p - a; }'
+try 2 'int main() { int a[4]; int *p = a + 3; p -= 1; return p - a; }'
+try 3 'int main() { int i = 2; ++i; return i; }'
+try 3 'int main() { int i = 2; return ++i; }'
+try 1 'int main() { int i = 2; return --i; }'
+try 2 'int main() { int i = 2; return i++; }'
+try 3 'int main() { int i = 2; i++; return i; }'
+try 2 'int main() { int i = 2; return i--; }'
+try 1 'int main() { int i = 2; i--; return i; }'
+try 0 'int main() { unsigned char c = 255; c++; return c; }'
+try 255 'int main() { unsigned char c = 255; return c++; }'
+try 1 'int main() { float f = 0.1f; float g = f++; return g == 0.1f; }'
+try 1 'int main() { double d = 1.5; d--; return d == 0.5; }'
+try 3 'int main() { int a[4]; a[0] = 1; a[1] = 2; a[2] = 3; int *p = a; p++; ++p; return *p; }'
+try 2 'int main() { int a[2]; a[0] = 1; a[1] = 2; int *p = a; *p++ = 5; return *p; }'
+try 5 'int main() { int a[2]; a[0] = 1; a[1] = 2; int *p = a; *p++ = 5; return a[0]; }'
+try 10 'int main() { int i; int s = 0; for (i = 0; i < 5; i++) s += i; return s; }'
+try 15 'int main() { int i = 5; int s = 0; while (i) s += i--; return s; }'
+try 1 'int n; int f() { n++; return 1; } int main() { int a[3]; a[1] = 4; a[f()] += 3; return n; }'
+try 7 'int n; int f() { n++; return 1; } int main() { int a[3]; a[1] = 4; a[f()] += 3; return a[1]; }'
+try 1 'int n; int f() { n++; return 0; } int main() { int a[1]; a[0] = 0; a[f()]++; return n; }'
+try 1 'int n; int f() { n++; return 0; } int main() { int a[1]; a[0] = 0; return ++a[f()] + n - 1; }'
+try 6 'struct { int a; char b; } s; int main() { s.a = 3; s.b = 2; s.a *= s.b; return s.a; }'
+try 4 'int main() { struct { int a; } s; struct { int a; } *p = &s; s.a = 3; p->a++; return s.a; }'
+try 2 'int main() { int x = 1; int *p = &x; (*p)++; return x; }'
+try 5 'int main() { int x = 2; switch (x) { case 1: return 4; case 2: return 5; case 3: return 6; } return 7; }'
+try 7 'int main() { int x = 9; switch (x) { case 1: return 4; case 2: return 5; } return 7; }'
+try 8 'int main() { switch (9) { case 1: return 4; default: return 8; case 2: return 5; } return 7; }'
+try 6 'int main() { int n = 0; switch (1) { case 0: n += 1; case 1: n += 2; case 2: n += 4; } return n; }'
+try 2 'int main() { int n = 0; switch (1) { case 0: n += 1; case 1: n += 2; break; case 2: n += 4; } return n; }'
+try 7 'int main() { int n = 0; for (int i = 0; i < 6; i++) switch (i) { case 1: case 3: continue; case 4: break; default: n += i; } return n; }'
+try 3 'int main() { int n = 0; for (int i = 0; i < 10; i++) { switch (i) { case 3: break; } if (i == 3) break; n++; } return n; }'
+try 31 'int main() { int n = 0; for (int i = -2; i < 8; i++) switch (i) { case -1: n += 1; break; case 0: n += 2; break; case 1: n += 4; break; case 2: n += 8; break; case 4: n += 16; break; } return n; }'
+try 42 'int f(int x) { switch (x) { case 10: return 1; case 11: return 2; case 12: return 3; case 13: return 4; case 15: return 5; default: return 42; } } int main() { return f(9) + f(14) - f(16); }'
+try 15 'int f(int x) { switch (x) { case 10: return 1; case 11: return 2; case 12: return 3; case 13: return 4; case 15: return 5; default: return 42; } } int main() { return f(10) + f(11) + f(12) + f(13) + f(15); }'
+try 3 'int main() { switch (3000000000u) { case 1: return 1; case 3000000000u: return 3; } return 0; }'
+try 4 'int main() { unsigned x = -1; switch (x) { case -1: return 4; case 0: return 5; case 1: return 6; case 2: return 7; } return 0; }'
+try 5 'int main() { char c = 2; switch (c) { case 258: return 4; case 2: return 5; } return 0; }'
+try 6 'int main() { switch (2) { case 1 + 1: return 6; case sizeof(int): return 7; } return 0; }'
+try 9 'int main() { int x = 1; switch (x) { case 1: { int y = 4; switch (y) { case 4: x += 5; break; } x += 3; } } return x; }'
+try 0 'int main() { int n = 0; switch (5) n = 3; return n; }'
+try 9 'int main() { long x = -1; switch (x) { case 0: return 1; case 1: return 2; case 2: return 3; case 3: return 4; } return 9; }'
+try 3 'int main() { int i = 0; goto a; a: i++; b: i++; c: i++; return i; }'
+try 1 'int main() { int i = 0; goto e; d: i++; e: i++; return i; }'
+try 1 'int main() { int i = 0; goto i; i: i++; return i; }'
+try 10 'int main() { int i = 0; loop: if (i < 10) { i++; goto loop; } return i; }'
+try 7 'int f() { goto end; return 1; end: return 7; } int main() { goto end; return 2; end: return f(); }'
+try 5 'int main() { int s = 0; for (int i = 0; i < 10; i++) for (int j = 0; j < 10; j++) { if (i + j == 5) goto out; s++; } out: return s; }'
+try 10 'int main() { int state = 0, n = 0; s0: n += 1; if (state++ < 3) goto s1; return n; s1: n += 2; goto s0; }'
+try 4 'int main() { int x = 4; x: return x; }'
+try 10 'int main() { int i = 0; do i++; while (i < 10); return i; }'
+try 1 'int main() { int i = 0; do i++; while (0); return i; }'
+try 1 'int main() { int i = 0; do { i++; } while (i > 5); return i; }'
+try 5 'int main() { int i = 0; do { if (i == 5) break; i++; } while (1); return i; }'
+try 3 'int main() { int i = 0, n = 0; do { i++; if (i > 3) continue; n++; } while (i < 10); return n; }'
+try 10 'int main() { int i = 0; do { i++; continue; i = 100; } while (i < 10); return i; }'
+try 6 'int main() { int n = 0, i = 0; do { int j = 0; do n++; while (++j < 2); } while (++i < 3); return n; }'
+try 4 'int main() { int i = 0; do switch (i) { case 2: i += 2; continue; default: i++; } while (i < 3); return i; }'
+try 2 'int main() { double d = 0.5; int i = 0; do i++; while (d *= 2, d < 2); return i; }'
+try 3 'int main() { int x = 3; double d = 1; do x; while (d = 0); return x; }'
+try 3 'typedef int myint; int main() { myint x = 3; return x; }'
+try 8 'typedef int *ptr; int main() { ptr p; return sizeof(p); }'
+try 12 'typedef int arr[3]; int main() { arr a; return sizeof(a); }'
+try 5 'typedef struct { int a; int b; } pair; int main() { pair p; p.a = 2; p.b = 3; return p.a + p.b; }'
+try 4 'typedef int t; int main() { typedef char t; return sizeof(t) + 3; }'
+try 7 'typedef int t; int main() { { int t = 7; return t; } }'
+try 2 'typedef int t, *tp; int main() { t a = 1; tp p = &a; *p = 2; return a; }'
+try 8 'typedef long l; int main() { l x = 3; return sizeof(x); }'
+try 0 'enum { A, B, C }; int main() { return A; }'
+try 2 'enum { A, B, C }; int main() { return C; }'
+try 11 'enum { A = 10, B, C = 3 }; int main() { return B; }'
+try 4 'enum { A = 3, B = A + 1 }; int main() { return B; }'
+try 6 'enum e { A, B }; int main() { enum e x = B; return sizeof(x) * x + 2; }'
+try 1 'int main() { enum { X, Y, }; return Y; }'
+try 5 'int main() { enum { A = -2, B, C, D, E, F }; return F - B + 1; }'
+try 3 'int main() { int x = 3; { enum { x = 5 }; } return x; }'
+try 2 'int main() { switch (1) { enum { P, Q }; case Q: return 2; } return 0; }'
+try 3 'int main() { const int x = 3; return x; }'
+try 4 'int main() { int const x = 4; return x; }'
+try 5 'int main() { int a = 0; const int *p = &a; a = 5; return *p; }'
+try 6 'int main() { int a = 0, b = 6; int *const p = &a; *p = b; return a; }'
+try 7 'int main() { const char *s = "abc"; s = "\a"; return *s; }'
+try 2 'const int g = 2; int main() { return g; }'
+try 9 'int main() { volatile int x = 9; return x; }'
+try 1 'int main() { int a = 1; int *restrict p = &a; return *p; }'
+try 3 'int f(const int x) { return x; } int main() { return f(3); }'
+try 3 'int f() { static int n; return ++n; } int main() { f(); f(); return f(); }'
+try 15 'int f() { static int n = 10; n += 5; return n; } int main() { f(); return f() - 5; }'
+try 3 'int f() { static int n = 1; return n++; } int g() { static int n = 2; return n++; } int main() { f(); return f() + g() - 1; }'
+try 8 'int *f() { static int a[2] = {3, 5}; return a; } int main() { return f()[0] + f()[1]; }'
+try 4 'static int g = 4; int main() { return g; }'
+try 5 'static int f() { return 5; } int main() { return f(); }'
+try 6 'inline int f() { return 6; } int main() { return f(); }'
+try 7 'int main() { extern int g; return g; } int g = 7;'
+try 8 'extern int g; int main() { return g; } int g = 8;'
+try 3 'extern int g; int g = 3; int main() { return g; }'
+
+fail 'int main() { break; }'
+fail 'int main() { if (1) continue; }'
+fail 'int main() { { 1; }'
+fail 'int main() { 1; } }'
+fail 'int main() { return 1; } 1;'
+fail 'return 1;'
+fail 'int main(int a, int b { return a; }'
+fail 'int main(1) { return 1; }'
+fail 'int main(int a, int b, int c, int d, int e, int f, int g) { return 0; }'
+fail 'int main() { return add6(1, 2, 3, 4, 5, 6, 7); }'
+fail 'int main() { return add(1, 2; }'
+fail 'main() { return 0; }'
+fail 'int main() { a = 1; return a; }'
+fail 'int main() { int a; int a; return 0; }'
+fail 'int main() { { int a; } return a; }'
+fail 'int main() { for (int i = 0; i < 3; i = i + 1) 1; return i; }'
+fail 'int f(a) { return a; }'
+fail 'int main() { int a; 1 = a; return 0; }'
+fail 'int main() { int a = 1, b = a + 1, ; return b; }'
+fail 'int main() { int x = 1; return *x; }'
+fail 'int main() { int x; int *p = &x; return p + p; }'
+fail 'int main() { int x; int *p = &x; return p * 2; }'
+fail 'int main() { return &1; }'
+fail 'int main() { int x; return 1 - &x; }'
+fail 'int main() { int a[2]; int b[2]; a = b; return 0; }'
+fail 'int main() { int a[x]; return 0; }'
+fail 'int main() { int a[2; return 0; }'
+fail 'int main() { int a[2]; return a[1; }'
+fail 'int main() { char *s = "abc; return 0; }'
+fail 'int main() { return "\x"[0]; }'
+fail 'int main() { "abc" = 0; return 0; }'
+fail 'int x; int x; int main() { return 0; }'
+fail 'int main() { return y; } int y;'
+fail 'int x; int y = x; int main() { return y; }'
+fail 'int a[2] = {1, 2, 3}; int main() { return 0; }'
+fail 'int main() { struct s x; return 0; }'
+fail 'int main() { struct s { struct s x; } y; return 0; }'
+fail 'int main() { struct { int a; } x; return x.b; }'
+fail 'int main() { int x; return x.a; }'
+fail 'int main() { int *p; return p->a; }'
+fail 'int main() { struct { int a; } x; return x + 1; }'
+fail 'int main() { struct { int a; } x; int y; x = y; return 0; }'
+fail 'int main() { struct { int a; int a; } x; return 0; }'
+fail 'int main() { return _Alignof(struct s); }'
+fail 'int main() { return 09; }'
+fail 'int main() { return 0x; }'
+fail 'int main() { return 1abc; }'
+fail 'int main() { return 99999999999999999999; }'
+fail 'int main() { return 9223372036854775808; }'
+fail 'int main() { short long x; return 0; }'
+fail 'int main() { char int x; return 0; }'
+fail 'int main() { long long long x; return 0; }'
+fail 'int main() { signed unsigned x; return 0; }'
+fail 'int main() { float double x; return 0; }'
+fail 'int main() { long float x; return 0; }'
+fail 'int main() { unsigned double x; return 0; }'
+fail 'int main() { int *p; double d; return p + d; }'
+fail 'int main() { int *p; p = 1.5; return 0; }'
+fail 'int main() { return 1.5x; }'
+fail 'int main() { return 1e; }'
+fail 'int main() { return 1.5 % 2; }'
+fail 'int main() { return 1 << 2.0; }'
+fail 'int main() { double d; return ~d; }'
+fail 'int main() { struct { int a; } s; return !s; }'
+fail 'int main() { return 1 ? 2; }'
+fail 'int main() { int *p; return 1 ? p : 2.0; }'
+fail 'int main() { return 1 ? : 2; }'
+fail 'int main() { 1 += 2; return 0; }'
+fail 'int main() { int a[2]; a += 1; return 0; }'
+fail 'int main() { int x; x++ = 3; return 0; }'
+fail 'int main() { int x; ++x++; return 0; }'
+fail 'int main() { double d; d %= 2; return 0; }'
+fail 'int main() { struct { int a; } s; s += 1; return 0; }'
+fail 'int main() { int *p; p *= 2; return 0; }'
+fail 'int main() { return +5++5; }'
+fail 'int main() { case 1: return 0; }'
+fail 'int main() { default: return 0; }'
+fail 'int main() { switch (1) { case 1: continue; } return 0; }'
+fail 'int main() { switch (1) { case 1: return 0; case 1: return 1; } }'
+fail 'int main() { switch (1) { case 1: return 0; case 2 - 1: return 1; } }'
+fail 'int main() { switch (1) { default: return 0; default: return 1; } }'
+fail 'int main() { int x; switch (1) { case x: return 0; } return 1; }'
+fail 'int main() { switch (1) { case 1.5: return 0; } return 1; }'
+fail 'int main() { switch (1.5) { case 1: return 0; } return 1; }'
+fail 'int main() { switch (1) { case 1 return 0; } return 1; }'
+fail 'int main() { goto a; return 0; }'
+fail 'int main() { a: a: return 0; }'
+fail 'int main() { a: return 0; b: a: return 1; }'
+fail 'int f() { a: return 0; } int main() { goto a; }'
+fail 'int main() { goto 1; return 0; }'
+fail 'int main() { a: }'
+fail 'int main() { do ; while (0); return 0; }'
+fail 'int main() { int i; do i++; while (0) return 0; }'
+fail 'int main() { int i; do i++; return 0; }'
+fail 'int main() { int i; do i++; while 1; return 0; }'
+fail 'int main() { typedef int t; t = 3; return 0; }'
+fail 'int main() { typedef int t; int t; return 0; }'
+fail 'int main() { typedef int t = 3; return 0; }'
+fail 'int main() { t x; return 0; }'
+fail 'typedef int t; int main() { t int x; return 0; }'
+fail 'int main() { enum e x; return 0; }'
+fail 'int main() { enum { A, A }; return 0; }'
+fail 'int main() { int A; enum { A }; return 0; }'
+fail 'int main() { int x; enum { A = x }; return 0; }'
+fail 'int main() { enum { A = 1.5 }; return 0; }'
+fail 'int main() { enum { A = 2147483648 }; return 0; }'
+fail 'int main() { enum { A = 2147483647, B }; return 0; }'
+fail 'struct s { int a; }; int main() { enum s x; return 0; }'
+fail 'int main() { const int x = 1; x = 2; return 0; }'
+fail 'int main() { const int x = 1; x++; return 0; }'
+fail 'int main() { const int x = 1; --x; return 0; }'
+fail 'int main() { const int x = 1; x += 2; return 0; }'
+fail 'int main() { int a; const int *p = &a; *p = 1; return 0; }'
+fail 'int main() { int a; const int *p = &a; p[0] = 1; return 0; }'
+fail 'int main() { const int a[2]; a[1] = 3; return 0; }'
+fail 'int main() { struct { const int a; } s; s.a = 1; return 0; }'
+fail 'int main() { const struct { int a; } s; s.a = 1; return 0; }'
+fail 'int main() { struct { int a; } s; const struct { int a; } *p = &s; p->a = 1; return 0; }'
+fail 'int main() { int *const p = 0; p = 0; return 0; }'
+fail 'const int g = 1; int main() { g = 2; return 0; }'
+fail 'int f(const int x) { x = 1; return x; } int main() { return f(0); }'
+fail 'int main() { static extern int x; return 0; }'
+fail 'int main() { typedef static int x; return 0; }'
+fail 'int f(static int x) { return x; } int main() { return 0; }'
+fail 'int main() { return sizeof(static int); }'
+fail 'int main() { int x; static int y = x; return 0; }'
+fail 'int main() { static int x; int x; return 0; }'
+fail 'int main() { struct { static int a; } s; return 0; }'
+fail 'int main() { return 1 $ 2; }'
+fail 'int main() { return 0; } @'
+fail 'int main() { return 0 ='
+fail 'int main() { return 0 !'
+fail 'int main() { return 0 <'
+fail 'int main() { return "abc'
+fail 'int main() { return 0x; }'
+
+fail_with 'error: use of undeclared identifier `x`
+ --> <input>:1:21
+  |
+1 | int main() { return x; }
+  |                     ^' 'int main() { return x; }'
+fail_with 'error: invalid operands of types `int *` and `int`
+ --> <input>:2:12
+  |
+2 |   return p * 2;
+  |            ^' 'int main() { int *p;
+  return p * 2;
+}'
+fail_with 'error: too many parameters
+ --> <input>:1:53
+  |
+1 | int f(int a, int b, int c, int d, int e, int f, int g) { return 0; }
+  |                                                     ^
+  = note: at most 6 are supported' 'int f(int a, int b, int c, int d, int e, int f, int g) { return 0; }'
+fail_with 'error: unexpected end of input
+ --> <input>:1:22
+  |
+1 | int main() { return 0
+  |                      ^' 'int main() { return 0'
+fail_with 'error: unexpected `;`
+ --> <input>:1:22
+  |
+1 | int main() { int x = ; return y; }
+  |                      ^
+
+error: use of undeclared identifier `y`
+ --> <input>:1:31
+  |
+1 | int main() { int x = ; return y; }
+  |                               ^' 'int main() { int x = ; return y; }'
+
+try 7 'int main() { int returned = 3, do_not = 4; return returned + do_not; }'
+try 5 'int integer; int iffy() { return 2; } int main() { integer = 3; return integer + iffy(); }'
+try 3 'int main() { int x = 2; x++; return x; }'
+fail_with 'error: unexpected `_Bool`
+ --> <input>:1:14
+  |
+1 | int main() { _Bool b; return 0; }
+  |              ^^^^^' 'int main() { _Bool b; return 0; }'
+
+try 3 'int main() {
+	// a line comment
+	int x = 1; /* a block
+	comment */ return x + /**/ 2; // at the end
+}'
+try 6 'int main() { return 12 / /* not a comment start */ 2; }'
+fail_with 'error: unterminated block comment
+ --> <input>:1:14
+  |
+1 | int main() { /* return 0; }
+  |              ^^' 'int main() { /* return 0; }'
+
+fail_with 'error: redefinition of `f`
+ --> <input>:1:24
+  |
+1 | int f(){return 1;} int f(){return 2;} int main(){return f();}
+  |                        ^' 'int f(){return 1;} int f(){return 2;} int main(){return f();}'
+
+fail 'int main() { int a[1000000000]; return 0; }'
+fail 'int main() { char a[3000000000]; return 0; }'
+fail 'int main() { char a[1000000000]; char b[100000000]; return 0; }'
+fail 'struct s { char a[1000000000]; char b[100000000]; }; int main() { return 0; }'
+
+fail 'struct S { int a; int b; }; int f(struct S s) { return s.a; } int main() { struct S x; x.a = 3; return f(x); }'
+fail 'struct S { int a; }; struct S f() { struct S x; x.a = 3; return x; } int main() { return 0; }'
+fail 'struct S { int a; }; int main() { struct S x; x.a = 3; printf("%d\n", x); return 0; }'
+fail 'struct S { int a; }; int main() { struct S x; x.a = 3; return x; }'
+
+try_output '-inf' 'int main() { double z = 0.0; double n = -z; printf("%f\n", 1.0 / n); return 0; }'
+try_output '-inf -0.000000' 'int main() { float z = 0.0; printf("%f %f\n", 1.0 / -z, -0.0); return 0; }'
+try_output '-0.000000' 'double g = -0.0; int main() { printf("%f\n", g); return 0; }'
+try 3 'int main() { double x = 1.5; return -(-x * 2); }'
+try 255 'int main() { char c = 1; return -c; }'
+try 1 'int main() { unsigned x = 1; return -x == 4294967295u; }'
+try 1 'int main() { long x = -1; return -x; }'
+try 1 'int g = -3; int main() { return g == 0 - 3; }'
+fail 'int main() { int *p = 0; -p; return 0; }'
+
+fail_with 'error: no room in the stack frame for the result of `+=`
+ --> <input>:1:39
+  |
+1 | int main() { char a[1073741824]; a[0] += 1; return 0; }
+  |                                       ^^
+  = note: objects and stack frames can be at most 1073741824 bytes' 'int main() { char a[1073741824]; a[0] += 1; return 0; }'
+
 echo OK
\ No newline at end of file